@@ -31,7 +31,7 @@ sol_storage! {
 #[inherit(ERC20<SampleParams>)]
 impl MyToken {
     pub fn mint(&mut self, amount: U256) -> Result<(), Vec<u8>> {
-        self.erc20.mint(msg::sender(), amount);
+        ERC20::mint(self, msg::sender(), amount)?;
 
         Ok(())
     }
@@ -18,6 +18,7 @@ def run_benchmark(address):
         'transferFrom(address,address,uint256,uint256)': ['(bool)', address, address, '1', '1'],
         'approve(address,uint256,uint256)': ['(bool)', address, '1', '1'],
         'setOperator(address,bool)': ['(bool)', address, 'true'],
+        'totalSupply(uint256)': ['(uint256)', '1'],
         'supportsInterface(bytes4)': ['(bool)', '0x80ac58cd']
     }
 
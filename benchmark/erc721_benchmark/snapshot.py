@@ -26,7 +26,9 @@ def run_benchmark(address):
         'transferFrom(address,address,uint256)': ['()', address, address, '1'],
         'safeTransferFrom(address,address,uint256)': ['()', address, address, '1'],
         'safeTransferFrom(address,address,uint256,bytes)': ['()', address, address, '1', '0x'],
-        'supportsInterface(bytes4)': ['(bool)', '0x80ac58cd']
+        'supportsInterface(bytes4)': ['(bool)', '0x80ac58cd'],
+        'mint(uint256)': ['()', '1000000'],
+        'mintConsecutive(uint256)': ['()', '100']
     }
 
     # Run estimates and write to snapshot file
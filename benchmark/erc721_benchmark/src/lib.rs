@@ -2,8 +2,9 @@
 #![cfg_attr(not(feature = "export-abi"), no_main)]
 extern crate alloc;
 
+use alloc::vec::Vec;
 use rustmate::tokens::erc721::{ERC721Params, ERC721};
-use stylus_sdk::{alloy_primitives::U256, prelude::*};
+use stylus_sdk::{alloy_primitives::U256, msg, prelude::*};
 
 pub struct SampleParams;
 
@@ -11,6 +12,7 @@ pub struct SampleParams;
 impl ERC721Params for SampleParams {
     const NAME: &'static str = "MyNFT";
     const SYMBOL: &'static str = "NFT";
+    const CONSECUTIVE_IDS: u64 = 1_000_000;
 
     fn token_uri(id: U256) -> String {
         format!("ipfs://hash/{}", id)
@@ -28,4 +30,16 @@ sol_storage! {
 
 #[external]
 #[inherit(ERC721<SampleParams>)]
-impl SampleNFT {}
+impl SampleNFT {
+    pub fn mint(&mut self, id: U256) -> Result<(), Vec<u8>> {
+        ERC721::<SampleParams>::mint(self, msg::sender(), id)?;
+
+        Ok(())
+    }
+
+    pub fn mint_consecutive(&mut self, quantity: U256) -> Result<(), Vec<u8>> {
+        ERC721::<SampleParams>::mint_consecutive(self, msg::sender(), quantity)?;
+
+        Ok(())
+    }
+}
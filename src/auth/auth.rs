@@ -5,6 +5,10 @@
 //!
 //! Note that this code is unaudited and not fit for production use.
 
+use crate::host::{
+    contract,
+    msg,
+};
 use alloy_primitives::{
     Address,
     FixedBytes,
@@ -18,9 +22,7 @@ use core::{
     marker::PhantomData,
 };
 use stylus_sdk::{
-    contract,
     evm,
-    msg,
     prelude::*,
 };
 
@@ -95,9 +97,9 @@ impl<T: AuthParams> Auth<T> {
     ) -> Result<bool> {
         let authority = storage.borrow_mut().authority.get();
 
-        return Ok(authority != Address::ZERO
+        Ok(authority != Address::ZERO
             && Self::can_call(storage, authority, user, contract::address(), function_sig)?
-            || user == storage.borrow_mut().owner.get());
+            || user == storage.borrow_mut().owner.get())
     }
 }
 
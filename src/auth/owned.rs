@@ -5,6 +5,7 @@
 //!
 //! Note that this code is unaudited and not fit for production use.
 
+use crate::host::msg;
 use alloc::vec::Vec;
 use alloy_primitives::Address;
 use alloy_sol_types::{
@@ -14,7 +15,6 @@ use alloy_sol_types::{
 use core::marker::PhantomData;
 use stylus_sdk::{
     evm,
-    msg,
     prelude::*,
 };
 
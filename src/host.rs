@@ -0,0 +1,21 @@
+//! Provides the environment accessors used by the contracts in this crate.
+//!
+//! The Stylus SDK caches values such as `msg::sender()` for the lifetime of the
+//! program. That is exactly right on chain, but it means a mocked host could never
+//! change them between calls. Contracts import [`msg`], [`block`] and [`contract`]
+//! from here instead, so that the `testing` feature can swap in uncached versions
+//! that read from [`crate::testing::Host`].
+
+#[cfg(not(any(test, feature = "testing")))]
+pub use stylus_sdk::{
+    block,
+    contract,
+    msg,
+};
+
+#[cfg(any(test, feature = "testing"))]
+pub use crate::testing::{
+    block,
+    contract,
+    msg,
+};
@@ -0,0 +1,297 @@
+//! Native definitions of the `vm_hooks` imports the Stylus SDK links against.
+//!
+//! Each hostio reads from or writes to the thread's mocked [`State`](super::State).
+//! Pointers are trusted to be valid, exactly as the Stylus VM trusts the program.
+
+use super::{
+    ecrecover,
+    mock_code_hash,
+    with_state,
+    Log,
+};
+use alloy_primitives::{
+    keccak256,
+    Address,
+    B256,
+    U256,
+};
+use core::slice;
+
+/// The `ecrecover` precompile, the only one the mocked host implements.
+const ECRECOVER: Address = Address::with_last_byte(1);
+
+unsafe fn read_address(ptr: *const u8) -> Address {
+    Address::from_slice(slice::from_raw_parts(ptr, 20))
+}
+
+unsafe fn read_word(ptr: *const u8) -> B256 {
+    B256::from_slice(slice::from_raw_parts(ptr, 32))
+}
+
+unsafe fn write(dest: *mut u8, bytes: &[u8]) {
+    slice::from_raw_parts_mut(dest, bytes.len()).copy_from_slice(bytes);
+}
+
+/// Performs a call against the mocked accounts, moving `value` from the contract.
+/// Returns the call's status and the length of its return data.
+fn call(target: Address, calldata: &[u8], value: U256) -> (u8, usize) {
+    let (status, data) = with_state(|state| {
+        let from = state.balances.get(&state.address).copied().unwrap_or_default();
+        if from < value {
+            return (1, Vec::new());
+        }
+        if value != U256::ZERO {
+            state.balances.insert(state.address, from - value);
+            *state.balances.entry(target).or_default() += value;
+        }
+
+        match state.calls.get(&target).cloned() {
+            Some(Ok(data)) => (0, data),
+            Some(Err(data)) => (1, data),
+            None if target == ECRECOVER => (0, ecrecover(calldata)),
+            None => (0, Vec::new()),
+        }
+    });
+
+    let len = data.len();
+    with_state(|state| state.return_data = data);
+    (status, len)
+}
+
+#[no_mangle]
+unsafe extern "C" fn account_balance(address: *const u8, dest: *mut u8) {
+    let address = read_address(address);
+    let balance = with_state(|state| state.balances.get(&address).copied().unwrap_or_default());
+    write(dest, &balance.to_be_bytes::<32>());
+}
+
+#[no_mangle]
+unsafe extern "C" fn account_codehash(address: *const u8, dest: *mut u8) {
+    let address = read_address(address);
+    let hash = with_state(|state| match state.calls.contains_key(&address) {
+        true => mock_code_hash(address),
+        false => B256::ZERO,
+    });
+    write(dest, hash.as_slice());
+}
+
+#[no_mangle]
+unsafe extern "C" fn storage_load_bytes32(key: *const u8, dest: *mut u8) {
+    let key = read_word(key);
+    let value = with_state(|state| state.storage.get(&key).copied().unwrap_or_default());
+    write(dest, value.as_slice());
+}
+
+#[no_mangle]
+unsafe extern "C" fn storage_store_bytes32(key: *const u8, value: *const u8) {
+    let (key, value) = (read_word(key), read_word(value));
+    with_state(|state| state.storage.insert(key, value));
+}
+
+#[no_mangle]
+unsafe extern "C" fn block_basefee(basefee: *mut u8) {
+    write(basefee, B256::ZERO.as_slice());
+}
+
+#[no_mangle]
+unsafe extern "C" fn chainid() -> u64 {
+    with_state(|state| state.chain_id)
+}
+
+#[no_mangle]
+unsafe extern "C" fn block_coinbase(coinbase: *mut u8) {
+    write(coinbase, Address::ZERO.as_slice());
+}
+
+#[no_mangle]
+unsafe extern "C" fn block_gas_limit() -> u64 {
+    u64::MAX
+}
+
+#[no_mangle]
+unsafe extern "C" fn block_number() -> u64 {
+    with_state(|state| state.number)
+}
+
+#[no_mangle]
+unsafe extern "C" fn block_timestamp() -> u64 {
+    with_state(|state| state.timestamp)
+}
+
+#[no_mangle]
+unsafe extern "C" fn call_contract(
+    contract: *const u8,
+    calldata: *const u8,
+    calldata_len: usize,
+    value: *const u8,
+    _gas: u64,
+    return_data_len: *mut usize,
+) -> u8 {
+    let value = U256::from_be_bytes(read_word(value).0);
+    let (status, len) = call(
+        read_address(contract),
+        slice::from_raw_parts(calldata, calldata_len),
+        value,
+    );
+    *return_data_len = len;
+    status
+}
+
+#[no_mangle]
+unsafe extern "C" fn contract_address(address: *mut u8) {
+    write(address, with_state(|state| state.address).as_slice());
+}
+
+#[no_mangle]
+unsafe extern "C" fn create1(
+    _code: *const u8,
+    _code_len: usize,
+    _endowment: *const u8,
+    contract: *mut u8,
+    revert_data_len: *mut usize,
+) {
+    write(contract, Address::ZERO.as_slice());
+    *revert_data_len = 0;
+}
+
+#[no_mangle]
+unsafe extern "C" fn create2(
+    _code: *const u8,
+    _code_len: usize,
+    _endowment: *const u8,
+    _salt: *const u8,
+    contract: *mut u8,
+    revert_data_len: *mut usize,
+) {
+    write(contract, Address::ZERO.as_slice());
+    *revert_data_len = 0;
+}
+
+#[no_mangle]
+unsafe extern "C" fn delegate_call_contract(
+    contract: *const u8,
+    calldata: *const u8,
+    calldata_len: usize,
+    _gas: u64,
+    return_data_len: *mut usize,
+) -> u8 {
+    let (status, len) = call(
+        read_address(contract),
+        slice::from_raw_parts(calldata, calldata_len),
+        U256::ZERO,
+    );
+    *return_data_len = len;
+    status
+}
+
+#[no_mangle]
+unsafe extern "C" fn emit_log(data: *const u8, len: usize, topics: usize) {
+    let bytes = slice::from_raw_parts(data, len);
+    let (topics, data) = bytes.split_at(topics * 32);
+    let log = Log {
+        topics: topics.chunks(32).map(B256::from_slice).collect(),
+        data: data.to_vec(),
+    };
+    with_state(|state| state.logs.push(log));
+}
+
+#[no_mangle]
+unsafe extern "C" fn evm_gas_left() -> u64 {
+    u64::MAX
+}
+
+#[no_mangle]
+unsafe extern "C" fn evm_ink_left() -> u64 {
+    u64::MAX
+}
+
+#[no_mangle]
+unsafe extern "C" fn memory_grow(_pages: u16) {}
+
+#[no_mangle]
+unsafe extern "C" fn msg_reentrant() -> bool {
+    false
+}
+
+#[no_mangle]
+unsafe extern "C" fn msg_sender(sender: *mut u8) {
+    write(sender, with_state(|state| state.sender).as_slice());
+}
+
+#[no_mangle]
+unsafe extern "C" fn msg_value(value: *mut u8) {
+    write(value, &with_state(|state| state.value).to_be_bytes::<32>());
+}
+
+#[no_mangle]
+unsafe extern "C" fn native_keccak256(bytes: *const u8, len: usize, output: *mut u8) {
+    write(output, keccak256(slice::from_raw_parts(bytes, len)).as_slice());
+}
+
+#[no_mangle]
+unsafe extern "C" fn read_args(_dest: *mut u8) {}
+
+#[no_mangle]
+unsafe extern "C" fn read_return_data(dest: *mut u8, offset: usize, size: usize) -> usize {
+    with_state(|state| {
+        let start = offset.min(state.return_data.len());
+        let end = offset.saturating_add(size).min(state.return_data.len());
+        write(dest, &state.return_data[start..end]);
+        end - start
+    })
+}
+
+#[no_mangle]
+unsafe extern "C" fn write_result(_data: *const u8, _len: usize) {}
+
+#[no_mangle]
+unsafe extern "C" fn return_data_size() -> usize {
+    with_state(|state| state.return_data.len())
+}
+
+#[no_mangle]
+unsafe extern "C" fn static_call_contract(
+    contract: *const u8,
+    calldata: *const u8,
+    calldata_len: usize,
+    _gas: u64,
+    return_data_len: *mut usize,
+) -> u8 {
+    let (status, len) = call(
+        read_address(contract),
+        slice::from_raw_parts(calldata, calldata_len),
+        U256::ZERO,
+    );
+    *return_data_len = len;
+    status
+}
+
+#[no_mangle]
+unsafe extern "C" fn tx_gas_price(gas_price: *mut u8) {
+    write(gas_price, B256::ZERO.as_slice());
+}
+
+#[no_mangle]
+unsafe extern "C" fn tx_ink_price() -> u32 {
+    1
+}
+
+#[no_mangle]
+unsafe extern "C" fn tx_origin(origin: *mut u8) {
+    write(origin, with_state(|state| state.sender).as_slice());
+}
+
+#[no_mangle]
+unsafe extern "C" fn log_f32(_value: f32) {}
+
+#[no_mangle]
+unsafe extern "C" fn log_f64(_value: f64) {}
+
+#[no_mangle]
+unsafe extern "C" fn log_i32(_value: i32) {}
+
+#[no_mangle]
+unsafe extern "C" fn log_i64(_value: i64) {}
+
+#[no_mangle]
+unsafe extern "C" fn log_txt(_text: *const u8, _len: usize) {}
@@ -0,0 +1,391 @@
+//! Provides a mocked Stylus host for running contracts natively with `cargo test`.
+//!
+//! The [`Host`] type owns an in-memory copy of everything a contract can observe:
+//! storage slots, the caller, the block, the contract's own address, account balances
+//! and the logs it emits. Create one at the start of a test, configure it, and call
+//! the contract's methods directly. Calls to the `ecrecover` precompile are served
+//! natively, and [`sign`] produces signatures for it.
+//!
+//! ```ignore
+//! let host = Host::new();
+//! let mut token = host.contract::<MyToken>();
+//!
+//! host.set_sender(alice);
+//! ERC20::transfer(&mut token, bob, amount).unwrap_ok();
+//! ```
+//!
+//! Only one [`Host`] can be alive at a time, so tests using it run one after another.
+//! This module is compiled for this crate's own tests and, for downstream crates,
+//! behind the `testing` feature. It only targets native builds.
+
+mod hostio;
+
+use alloc::vec::Vec;
+use alloy_primitives::{
+    address,
+    hex,
+    keccak256,
+    Address,
+    B256,
+    U256,
+};
+use alloy_sol_types::SolEvent;
+use core::cell::RefCell;
+use k256::ecdsa::{
+    RecoveryId,
+    Signature,
+    SigningKey,
+    VerifyingKey,
+};
+use std::collections::HashMap;
+use std::sync::{
+    Mutex,
+    MutexGuard,
+};
+use stylus_sdk::{
+    prelude::*,
+    storage::StorageCache,
+};
+
+/// Serializes tests, since the SDK's storage cache is shared by every thread.
+static LOCK: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State::default());
+}
+
+/// The mocked chain state read and written by [`hostio`].
+#[derive(Default)]
+struct State {
+    storage: HashMap<B256, B256>,
+    sender: Address,
+    value: U256,
+    timestamp: u64,
+    number: u64,
+    chain_id: u64,
+    address: Address,
+    balances: HashMap<Address, U256>,
+    calls: HashMap<Address, core::result::Result<Vec<u8>, Vec<u8>>>,
+    return_data: Vec<u8>,
+    logs: Vec<Log>,
+}
+
+fn with_state<R>(f: impl FnOnce(&mut State) -> R) -> R {
+    STATE.with(|state| f(&mut state.borrow_mut()))
+}
+
+/// Accounts that tests can act as or send to, without keys of their own.
+pub const ALICE: Address = address!("00000000000000000000000000000000000a11ce");
+pub const BOB: Address = address!("0000000000000000000000000000000000000b0b");
+pub const CAROL: Address = address!("00000000000000000000000000000000000ca201");
+
+/// A log emitted through `evm::log` or `evm::raw_log`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Log {
+    pub topics: Vec<B256>,
+    pub data: Vec<u8>,
+}
+
+impl Log {
+    /// Encodes `event` the same way `evm::log` does.
+    pub fn from_event<E: SolEvent>(event: &E) -> Self {
+        Self {
+            topics: event.encode_topics().into_iter().map(|topic| topic.0).collect(),
+            data: event.encode_data(),
+        }
+    }
+}
+
+/// A handle on the mocked host. Dropping it releases the host for the next test.
+pub struct Host {
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl Host {
+    /// Takes the host and resets it to an empty chain.
+    pub fn new() -> Self {
+        let guard = LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        StorageCache::clear();
+        with_state(|state| *state = State::default());
+
+        Self { _guard: guard }
+    }
+
+    /// Returns the contract type `S` rooted at slot zero, as the entrypoint would be.
+    pub fn contract<S: StorageType>(&self) -> S {
+        unsafe { S::new(U256::ZERO, 0) }
+    }
+
+    /// Sets the value returned by `msg::sender()`.
+    pub fn set_sender(&self, sender: Address) {
+        with_state(|state| state.sender = sender);
+    }
+
+    /// Sets the value returned by `msg::value()`.
+    pub fn set_value(&self, value: U256) {
+        with_state(|state| state.value = value);
+    }
+
+    /// Sets the value returned by `block::timestamp()`.
+    pub fn set_timestamp(&self, timestamp: u64) {
+        with_state(|state| state.timestamp = timestamp);
+    }
+
+    /// Sets the value returned by `block::number()`.
+    pub fn set_block_number(&self, number: u64) {
+        with_state(|state| state.number = number);
+    }
+
+    /// Sets the value returned by `block::chainid()`.
+    pub fn set_chain_id(&self, chain_id: u64) {
+        with_state(|state| state.chain_id = chain_id);
+    }
+
+    /// Sets the value returned by `contract::address()`.
+    pub fn set_contract_address(&self, address: Address) {
+        with_state(|state| state.address = address);
+    }
+
+    /// Sets the ether balance of `account`.
+    pub fn set_balance(&self, account: Address, balance: U256) {
+        with_state(|state| state.balances.insert(account, balance));
+    }
+
+    /// Returns the ether balance of `account`.
+    pub fn balance(&self, account: Address) -> U256 {
+        with_state(|state| state.balances.get(&account).copied().unwrap_or_default())
+    }
+
+    /// Makes every call to `target` return `result`, and gives `target` code.
+    /// Calls to any other account, bar the `ecrecover` precompile, succeed with
+    /// empty return data.
+    pub fn mock_call(&self, target: Address, result: core::result::Result<Vec<u8>, Vec<u8>>) {
+        with_state(|state| state.calls.insert(target, result));
+    }
+
+    /// Returns the persistent value of a storage slot.
+    pub fn storage(&self, slot: U256) -> B256 {
+        StorageCache::flush();
+        with_state(|state| {
+            state
+                .storage
+                .get(&B256::from(slot))
+                .copied()
+                .unwrap_or_default()
+        })
+    }
+
+    /// Returns every log emitted since the host was created.
+    pub fn logs(&self) -> Vec<Log> {
+        with_state(|state| state.logs.clone())
+    }
+
+    /// Whether `event` was emitted since the host was created.
+    pub fn emitted<E: SolEvent>(&self, event: &E) -> bool {
+        let log = Log::from_event(event);
+        with_state(|state| state.logs.contains(&log))
+    }
+}
+
+impl Default for Host {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Unwraps the results of contract methods, whose errors only implement `Into<Vec<u8>>`.
+pub trait ResultExt<T> {
+    /// Returns the success value, panicking with the revert data otherwise.
+    fn unwrap_ok(self) -> T;
+
+    /// Returns the revert data, panicking if the call succeeded.
+    fn unwrap_revert(self) -> Vec<u8>;
+}
+
+impl<T, E: Into<Vec<u8>>> ResultExt<T> for core::result::Result<T, E> {
+    #[track_caller]
+    fn unwrap_ok(self) -> T {
+        match self {
+            Ok(value) => value,
+            Err(err) => panic!("call reverted with 0x{}", hex::encode(err.into())),
+        }
+    }
+
+    #[track_caller]
+    fn unwrap_revert(self) -> Vec<u8> {
+        match self {
+            Ok(_) => panic!("call did not revert"),
+            Err(err) => err.into(),
+        }
+    }
+}
+
+/// Uncached replacement for [`stylus_sdk::msg`].
+pub mod msg {
+    use super::with_state;
+    use alloy_primitives::{
+        Address,
+        U256,
+    };
+
+    pub use stylus_sdk::msg::*;
+
+    /// Gets the caller set with [`Host::set_sender`](super::Host::set_sender).
+    pub fn sender() -> Address {
+        with_state(|state| state.sender)
+    }
+
+    /// Gets the value set with [`Host::set_value`](super::Host::set_value).
+    pub fn value() -> U256 {
+        with_state(|state| state.value)
+    }
+}
+
+/// Uncached replacement for [`stylus_sdk::block`].
+pub mod block {
+    use super::with_state;
+
+    pub use stylus_sdk::block::*;
+
+    /// Gets the chain id set with [`Host::set_chain_id`](super::Host::set_chain_id).
+    pub fn chainid() -> u64 {
+        with_state(|state| state.chain_id)
+    }
+
+    /// Gets the block number set with [`Host::set_block_number`](super::Host::set_block_number).
+    pub fn number() -> u64 {
+        with_state(|state| state.number)
+    }
+
+    /// Gets the timestamp set with [`Host::set_timestamp`](super::Host::set_timestamp).
+    pub fn timestamp() -> u64 {
+        with_state(|state| state.timestamp)
+    }
+}
+
+/// Uncached replacement for [`stylus_sdk::contract`].
+pub mod contract {
+    use super::with_state;
+    use alloy_primitives::Address;
+
+    pub use stylus_sdk::contract::*;
+
+    /// Gets the address set with [`Host::set_contract_address`](super::Host::set_contract_address).
+    pub fn address() -> Address {
+        with_state(|state| state.address)
+    }
+}
+
+/// Returns the address controlled by the secp256k1 private key `key`.
+pub fn signer(key: B256) -> Address {
+    let key = SigningKey::from_slice(key.as_slice()).expect("invalid private key");
+    to_address(key.verifying_key())
+}
+
+/// Signs `digest` with the secp256k1 private key `key`, returning `(v, r, s)`.
+/// Signatures are deterministic and always have a low `s`.
+pub fn sign(key: B256, digest: B256) -> (u8, B256, B256) {
+    let key = SigningKey::from_slice(key.as_slice()).expect("invalid private key");
+    let (signature, recovery_id) = key
+        .sign_prehash_recoverable(digest.as_slice())
+        .expect("signing failed");
+    let (r, s) = signature.split_bytes();
+
+    (
+        27 + recovery_id.to_byte(),
+        B256::from_slice(&r),
+        B256::from_slice(&s),
+    )
+}
+
+fn to_address(key: &VerifyingKey) -> Address {
+    let point = key.to_encoded_point(false);
+    Address::from_word(keccak256(&point.as_bytes()[1..]))
+}
+
+/// Implements the `ecrecover` precompile, returning no data for invalid signatures.
+fn ecrecover(input: &[u8]) -> Vec<u8> {
+    let mut input = input.to_vec();
+    input.resize(128, 0);
+
+    let v = U256::from_be_bytes::<32>(input[32..64].try_into().unwrap());
+    if v != U256::from(27) && v != U256::from(28) {
+        return Vec::new();
+    }
+    let Ok(mut signature) = Signature::from_slice(&input[64..128]) else {
+        return Vec::new();
+    };
+    let mut recovery_id = RecoveryId::new(v == U256::from(28), false);
+
+    // The precompile accepts high `s` values, which k256 refuses to recover.
+    if let Some(normalized) = signature.normalize_s() {
+        signature = normalized;
+        recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), false);
+    }
+
+    match VerifyingKey::recover_from_prehash(&input[0..32], &signature, recovery_id) {
+        Ok(key) => to_address(&key).into_word().to_vec(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Stand-in code hash for accounts given code through [`Host::mock_call`].
+fn mock_code_hash(account: Address) -> B256 {
+    keccak256(account)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{
+        address,
+        b256,
+    };
+    use alloy_sol_types::{
+        sol,
+        SolType,
+    };
+    use stylus_sdk::call::RawCall;
+
+    // The "Ether Mail" example from EIP-712, signed by `keccak256("cow")`.
+    const COW: B256 = b256!("c85ef7d79691fe79573b1a7064c19c1a9819ebdbd1faaab1a8ec92344438aaf4");
+    const COW_ADDRESS: Address = address!("cd2a3d9f938e13cd947ec05abc7fe734df8dd826");
+    const MAIL_DIGEST: B256 =
+        b256!("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2");
+    const MAIL_R: B256 = b256!("4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d");
+    const MAIL_S: B256 = b256!("07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562");
+
+    fn call_ecrecover(digest: B256, v: u8, r: B256, s: B256) -> Vec<u8> {
+        let data = <sol! { (bytes32, uint8, bytes32, bytes32) }>::encode(&(*digest, v, *r, *s));
+        RawCall::new_static()
+            .call(Address::with_last_byte(1), &data)
+            .unwrap()
+    }
+
+    #[test]
+    fn signer_of_known_key() {
+        assert_eq!(signer(COW), COW_ADDRESS);
+    }
+
+    #[test]
+    fn sign_known_vector() {
+        assert_eq!(sign(COW, MAIL_DIGEST), (28, MAIL_R, MAIL_S));
+    }
+
+    #[test]
+    fn ecrecover_known_vector() {
+        let _host = Host::new();
+
+        let recovered = call_ecrecover(MAIL_DIGEST, 28, MAIL_R, MAIL_S);
+
+        assert_eq!(recovered, COW_ADDRESS.into_word().to_vec());
+    }
+
+    #[test]
+    fn ecrecover_invalid_v() {
+        let _host = Host::new();
+
+        assert!(call_ecrecover(MAIL_DIGEST, 29, MAIL_R, MAIL_S).is_empty());
+    }
+}
@@ -6,8 +6,16 @@
 //! You can configure the behavior of [`ERC1155`] via the [`ERC1155Params`] trait,
 //! which allows specifying the name, symbol, and token uri.
 //!
+//! Extensions follow every mint, burn and transfer through the
+//! [`ERC1155Params::before_token_transfer`] and
+//! [`ERC1155Params::after_token_transfer`] hooks. So that the hooks can reach the
+//! inheriting contract, `mint`, `batch_mint`, `burn` and `batch_burn` take its
+//! storage rather than `&mut self`: call them as
+//! `ERC1155::<MyParams>::mint(self, to, id, amount, data)`.
+//!
 //! Note that this code is unaudited and not fit for production use.
 
+use crate::host::msg;
 use alloc::{
     string::String,
     vec::Vec,
@@ -27,12 +35,44 @@ use core::{
 use stylus_sdk::{
     abi::Bytes,
     evm,
-    msg,
     prelude::*,
 };
 
-pub trait ERC1155Params {
+pub trait ERC1155Params: Sized {
+    /// Whether the inheriting contract also inherits [`ERC2981`], so that
+    /// `supportsInterface` reports it.
+    ///
+    /// [`ERC2981`]: crate::tokens::royalty::ERC2981
+    const ROYALTIES: bool = false;
+
     fn uri(id: U256) -> String;
+
+    /// Called before each of `amounts` of the matching `ids` moves from `from` to
+    /// `to`, where `from` is zero for a mint and `to` is zero for a burn. The two
+    /// slices always have the same length. `storage` is the contract inheriting
+    /// [`ERC1155`]. Returning `Err` reverts the transfer with the given data.
+    fn before_token_transfer<S: TopLevelStorage + BorrowMut<ERC1155<Self>>>(
+        _storage: &mut S,
+        _from: Address,
+        _to: Address,
+        _ids: &[U256],
+        _amounts: &[U256],
+    ) -> Result<(), Vec<u8>> {
+        Ok(())
+    }
+
+    /// Called after the tokens have moved, but before the recipient is asked to
+    /// accept them, with the same arguments as
+    /// [`ERC1155Params::before_token_transfer`].
+    fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC1155<Self>>>(
+        _storage: &mut S,
+        _from: Address,
+        _to: Address,
+        _ids: &[U256],
+        _amounts: &[U256],
+    ) -> Result<(), Vec<u8>> {
+        Ok(())
+    }
 }
 
 sol_storage! {
@@ -67,6 +107,7 @@ sol! {
     error NotAuthorized();
     error UnsafeRecipient();
     error LengthMismatch();
+    error ERC1155InsufficientBalance(address sender, uint256 balance, uint256 needed, uint256 tokenId);
 }
 
 /// Represents the ways methods may fail.
@@ -75,6 +116,9 @@ pub enum ERC1155Error {
     CallFailed(stylus_sdk::call::Error),
     UnsafeRecipient(UnsafeRecipient),
     LengthMismatch(LengthMismatch),
+    ERC1155InsufficientBalance(ERC1155InsufficientBalance),
+    /// A transfer hook of the [`ERC1155Params`] failed with the given revert data.
+    HookFailed(Vec<u8>),
 }
 
 impl From<stylus_sdk::call::Error> for ERC1155Error {
@@ -91,6 +135,8 @@ impl From<ERC1155Error> for Vec<u8> {
             ERC1155Error::NotAuthorized(err) => err.encode(),
             ERC1155Error::UnsafeRecipient(err) => err.encode(),
             ERC1155Error::LengthMismatch(err) => err.encode(),
+            ERC1155Error::ERC1155InsufficientBalance(err) => err.encode(),
+            ERC1155Error::HookFailed(err) => err,
         }
     }
 }
@@ -149,15 +195,36 @@ impl<T: ERC1155Params> ERC1155<T> {
         Ok(())
     }
 
-    pub fn mint<S: TopLevelStorage>(
-        &mut self,
+    fn before_token_transfer<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        ids: &[U256],
+        amounts: &[U256],
+    ) -> Result<()> {
+        T::before_token_transfer(storage, from, to, ids, amounts).map_err(ERC1155Error::HookFailed)
+    }
+
+    fn after_token_transfer<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        ids: &[U256],
+        amounts: &[U256],
+    ) -> Result<()> {
+        T::after_token_transfer(storage, from, to, ids, amounts).map_err(ERC1155Error::HookFailed)
+    }
+
+    pub fn mint<S: TopLevelStorage + BorrowMut<Self>>(
         storage: &mut S,
         to: Address,
         id: U256,
         amount: U256,
         data: Bytes,
     ) -> Result<()> {
-        let mut to_balance = self.balance_of.setter(to);
+        Self::before_token_transfer(storage, Address::ZERO, to, &[id], &[amount])?;
+
+        let mut to_balance = storage.borrow_mut().balance_of.setter(to);
         let balance = to_balance.get(id) + amount;
         to_balance.insert(id, balance);
 
@@ -169,13 +236,12 @@ impl<T: ERC1155Params> ERC1155<T> {
             amount,
         });
 
-        Self::call_receiver(storage, id, Address::ZERO, to, amount, data.0)?;
+        Self::after_token_transfer(storage, Address::ZERO, to, &[id], &[amount])?;
 
-        Ok(())
+        Self::call_receiver(storage, id, Address::ZERO, to, amount, data.0)
     }
 
-    pub fn batch_mint<S: TopLevelStorage>(
-        &mut self,
+    pub fn batch_mint<S: TopLevelStorage + BorrowMut<Self>>(
         storage: &mut S,
         to: Address,
         ids: Vec<U256>,
@@ -186,10 +252,12 @@ impl<T: ERC1155Params> ERC1155<T> {
             return Err(ERC1155Error::LengthMismatch(LengthMismatch {}));
         }
 
+        Self::before_token_transfer(storage, Address::ZERO, to, &ids, &amounts)?;
+
         for i in 0..ids.len() {
             let id: U256 = ids[i];
 
-            let mut to_balance = self.balance_of.setter(to);
+            let mut to_balance = storage.borrow_mut().balance_of.setter(to);
             let balance = to_balance.get(id) + amounts[i];
             to_balance.insert(id, balance);
         }
@@ -202,37 +270,77 @@ impl<T: ERC1155Params> ERC1155<T> {
             amounts: amounts.clone(),
         });
 
+        Self::after_token_transfer(storage, Address::ZERO, to, &ids, &amounts)?;
+
         Self::call_receiver_batch(storage, ids, Address::ZERO, to, amounts, data.0)
     }
 
-    pub fn batch_burn(&mut self, from: Address, ids: Vec<U256>, amounts: Vec<U256>) -> Result<()> {
+    pub fn batch_burn<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        ids: Vec<U256>,
+        amounts: Vec<U256>,
+    ) -> Result<()> {
         if ids.len() != amounts.len() {
             return Err(ERC1155Error::LengthMismatch(LengthMismatch {}));
         }
 
+        Self::before_token_transfer(storage, from, Address::ZERO, &ids, &amounts)?;
+
         for i in 0..ids.len() {
             let id: U256 = ids[i];
 
-            let mut from_balance = self.balance_of.setter(from);
-            let balance = from_balance.get(id) - amounts[i];
-            from_balance.insert(id, balance);
+            let mut from_balance = storage.borrow_mut().balance_of.setter(from);
+            let balance = from_balance.get(id);
+
+            if balance < amounts[i] {
+                return Err(ERC1155Error::ERC1155InsufficientBalance(
+                    ERC1155InsufficientBalance {
+                        sender: from,
+                        balance,
+                        needed: amounts[i],
+                        tokenId: id,
+                    },
+                ));
+            }
+
+            from_balance.insert(id, balance - amounts[i]);
         }
 
         evm::log(TransferBatch {
             operator: msg::sender(),
             from,
             to: Address::ZERO,
-            ids,
-            amounts,
+            ids: ids.clone(),
+            amounts: amounts.clone(),
         });
 
-        Ok(())
+        Self::after_token_transfer(storage, from, Address::ZERO, &ids, &amounts)
     }
 
-    pub fn burn(&mut self, from: Address, id: U256, amount: U256) -> Result<()> {
-        let mut from_balance = self.balance_of.setter(from);
-        let balance = from_balance.get(id) - amount;
-        from_balance.insert(id, balance);
+    pub fn burn<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        id: U256,
+        amount: U256,
+    ) -> Result<()> {
+        Self::before_token_transfer(storage, from, Address::ZERO, &[id], &[amount])?;
+
+        let mut from_balance = storage.borrow_mut().balance_of.setter(from);
+        let balance = from_balance.get(id);
+
+        if balance < amount {
+            return Err(ERC1155Error::ERC1155InsufficientBalance(
+                ERC1155InsufficientBalance {
+                    sender: from,
+                    balance,
+                    needed: amount,
+                    tokenId: id,
+                },
+            ));
+        }
+
+        from_balance.insert(id, balance - amount);
 
         evm::log(TransferSingle {
             operator: msg::sender(),
@@ -242,7 +350,7 @@ impl<T: ERC1155Params> ERC1155<T> {
             amount,
         });
 
-        Ok(())
+        Self::after_token_transfer(storage, from, Address::ZERO, &[id], &[amount])
     }
 }
 
@@ -284,7 +392,7 @@ impl<T: ERC1155Params> ERC1155<T> {
         data: Bytes,
     ) -> Result<()> {
         if msg::sender() != from
-            || !storage
+            && !storage
                 .borrow_mut()
                 .is_approved_for_all
                 .getter(from)
@@ -293,9 +401,23 @@ impl<T: ERC1155Params> ERC1155<T> {
             return Err(ERC1155Error::NotAuthorized(NotAuthorized {}));
         }
 
+        Self::before_token_transfer(storage, from, to, &[id], &[amount])?;
+
         let mut from_balance = storage.borrow_mut().balance_of.setter(from);
-        let balance = from_balance.get(id) - amount;
-        from_balance.insert(id, balance);
+        let balance = from_balance.get(id);
+
+        if balance < amount {
+            return Err(ERC1155Error::ERC1155InsufficientBalance(
+                ERC1155InsufficientBalance {
+                    sender: from,
+                    balance,
+                    needed: amount,
+                    tokenId: id,
+                },
+            ));
+        }
+
+        from_balance.insert(id, balance - amount);
 
         let mut to_balance = storage.borrow_mut().balance_of.setter(to);
         let balance = to_balance.get(id) + amount;
@@ -309,6 +431,8 @@ impl<T: ERC1155Params> ERC1155<T> {
             amount,
         });
 
+        Self::after_token_transfer(storage, from, to, &[id], &[amount])?;
+
         Self::call_receiver(storage, id, from, to, amount, data.0)
     }
 
@@ -325,7 +449,7 @@ impl<T: ERC1155Params> ERC1155<T> {
         }
 
         if msg::sender() != from
-            || !storage
+            && !storage
                 .borrow_mut()
                 .is_approved_for_all
                 .getter(from)
@@ -334,13 +458,27 @@ impl<T: ERC1155Params> ERC1155<T> {
             return Err(ERC1155Error::NotAuthorized(NotAuthorized {}));
         }
 
+        Self::before_token_transfer(storage, from, to, &ids, &amounts)?;
+
         for i in 0..ids.len() {
             let id: U256 = ids[i];
             let amount: U256 = amounts[i];
 
             let mut from_balance = storage.borrow_mut().balance_of.setter(from);
-            let balance = from_balance.get(id) - amount;
-            from_balance.insert(id, balance);
+            let balance = from_balance.get(id);
+
+            if balance < amount {
+                return Err(ERC1155Error::ERC1155InsufficientBalance(
+                    ERC1155InsufficientBalance {
+                        sender: from,
+                        balance,
+                        needed: amount,
+                        tokenId: id,
+                    },
+                ));
+            }
+
+            from_balance.insert(id, balance - amount);
 
             let mut to_balance = storage.borrow_mut().balance_of.setter(to);
             let balance = to_balance.get(id) + amount;
@@ -355,6 +493,8 @@ impl<T: ERC1155Params> ERC1155<T> {
             amounts: amounts.clone(),
         });
 
+        Self::after_token_transfer(storage, from, to, &ids, &amounts)?;
+
         Self::call_receiver_batch(storage, ids, from, to, amounts, data.0)
     }
 
@@ -375,7 +515,8 @@ impl<T: ERC1155Params> ERC1155<T> {
     pub fn supports_interface(interface: [u8; 4]) -> Result<bool> {
         let supported = interface == 0x01ffc9a7u32.to_be_bytes() // ERC165 Interface ID for ERC165
             || interface == 0xd9b67a26u32.to_be_bytes() // ERC165 Interface ID for ERC1155
-            || interface == 0x0e89341cu32.to_be_bytes(); // ERC165 Interface ID for ERC1155MetadataURI
+            || interface == 0x0e89341cu32.to_be_bytes() // ERC165 Interface ID for ERC1155MetadataURI
+            || (T::ROYALTIES && interface == 0x2a55205au32.to_be_bytes()); // ERC165 Interface ID for ERC2981
         Ok(supported)
     }
 }
@@ -399,3 +540,157 @@ sol_interface! {
         ) external returns (bytes4);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        Host,
+        ResultExt,
+        ALICE,
+        BOB,
+    };
+    use alloc::vec;
+
+    struct TestParams;
+
+    impl ERC1155Params for TestParams {
+        fn uri(_id: U256) -> String {
+            String::new()
+        }
+    }
+
+    sol_storage! {
+        pub struct TestEditions {
+            #[borrow]
+            ERC1155<TestParams> erc1155;
+        }
+    }
+
+    unsafe impl TopLevelStorage for TestEditions {}
+
+    type Token = ERC1155<TestParams>;
+
+    /// Mints 100 of id 1 and 10 of id 2 to `ALICE`, a contract accepting the tokens.
+    fn setup() -> (Host, TestEditions) {
+        let host = Host::new();
+        let mut editions = host.contract::<TestEditions>();
+
+        let mut word = [0u8; 32];
+        word[..4].copy_from_slice(&0xbc197c81u32.to_be_bytes());
+        host.mock_call(ALICE, Ok(word.to_vec()));
+        Token::batch_mint(
+            &mut editions,
+            ALICE,
+            vec![U256::from(1), U256::from(2)],
+            vec![U256::from(100), U256::from(10)],
+            Bytes(vec![]),
+        )
+        .unwrap_ok();
+
+        host.set_sender(ALICE);
+        (host, editions)
+    }
+
+    fn insufficient_balance(balance: u64, needed: u64, id: u64) -> Vec<u8> {
+        ERC1155InsufficientBalance {
+            sender: ALICE,
+            balance: U256::from(balance),
+            needed: U256::from(needed),
+            tokenId: U256::from(id),
+        }
+        .encode()
+    }
+
+    #[test]
+    fn burn_insufficient_balance() {
+        let (_host, mut editions) = setup();
+
+        let revert =
+            Token::burn(&mut editions, ALICE, U256::from(2), U256::from(11)).unwrap_revert();
+
+        assert_eq!(revert, insufficient_balance(10, 11, 2));
+        assert_eq!(editions.erc1155.balance_of(ALICE, U256::from(2)).unwrap_ok(), U256::from(10));
+    }
+
+    #[test]
+    fn batch_burn_insufficient_balance() {
+        let (_host, mut editions) = setup();
+
+        let revert = Token::batch_burn(
+            &mut editions,
+            ALICE,
+            vec![U256::from(1), U256::from(2)],
+            vec![U256::from(100), U256::from(11)],
+        )
+        .unwrap_revert();
+
+        assert_eq!(revert, insufficient_balance(10, 11, 2));
+    }
+
+    #[test]
+    fn safe_transfer_from_insufficient_balance() {
+        let (_host, mut editions) = setup();
+
+        let revert = Token::safe_transfer_from(
+            &mut editions,
+            ALICE,
+            BOB,
+            U256::from(1),
+            U256::from(101),
+            Bytes(vec![]),
+        )
+        .unwrap_revert();
+
+        assert_eq!(revert, insufficient_balance(100, 101, 1));
+        assert_eq!(editions.erc1155.balance_of(BOB, U256::from(1)).unwrap_ok(), U256::ZERO);
+    }
+
+    #[test]
+    fn safe_batch_transfer_from_insufficient_balance() {
+        let (_host, mut editions) = setup();
+
+        let revert = Token::safe_batch_transfer_from(
+            &mut editions,
+            ALICE,
+            BOB,
+            vec![U256::from(1), U256::from(2)],
+            vec![U256::from(50), U256::from(11)],
+            Bytes(vec![]),
+        )
+        .unwrap_revert();
+
+        assert_eq!(revert, insufficient_balance(10, 11, 2));
+    }
+
+    #[test]
+    fn safe_transfer_from_authorization() {
+        let (host, mut editions) = setup();
+        host.set_sender(BOB);
+
+        let revert = Token::safe_transfer_from(
+            &mut editions,
+            ALICE,
+            BOB,
+            U256::from(1),
+            U256::from(1),
+            Bytes(vec![]),
+        )
+        .unwrap_revert();
+        assert_eq!(revert, NotAuthorized {}.encode());
+
+        host.set_sender(ALICE);
+        Token::safe_transfer_from(
+            &mut editions,
+            ALICE,
+            BOB,
+            U256::from(1),
+            U256::from(40),
+            Bytes(vec![]),
+        )
+        .unwrap_ok();
+
+        assert_eq!(editions.erc1155.balance_of(ALICE, U256::from(1)).unwrap_ok(), U256::from(60));
+        assert_eq!(editions.erc1155.balance_of(BOB, U256::from(1)).unwrap_ok(), U256::from(40));
+    }
+}
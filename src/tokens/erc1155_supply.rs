@@ -0,0 +1,308 @@
+//! Provides a supply tracking extension for the [`ERC1155`] token.
+//!
+//! The [`ERC1155Supply`] type keeps the total supply of each id and of all ids
+//! together, so that `totalSupply` and `exists` can answer without replaying
+//! transfer logs.
+//!
+//! The supplies follow every mint and burn through the [`ERC1155Params`] transfer
+//! hooks, so the token's params forward [`ERC1155Params::after_token_transfer`] to
+//! [`ERC1155Supply::after_token_transfer`], and [`ERC1155Supply`] is inherited
+//! alongside [`ERC1155`]:
+//!
+//! ```ignore
+//! impl ERC1155Params for MyParams {
+//!     // ...
+//!
+//!     fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC1155<Self>>>(
+//!         _storage: &mut S,
+//!         from: Address,
+//!         to: Address,
+//!         ids: &[U256],
+//!         amounts: &[U256],
+//!     ) -> Result<(), Vec<u8>> {
+//!         ERC1155Supply::<Self>::after_token_transfer(from, to, ids, amounts);
+//!         Ok(())
+//!     }
+//! }
+//!
+//! sol_storage! {
+//!     #[entrypoint]
+//!     pub struct MyEditions {
+//!         #[borrow]
+//!         ERC1155<MyParams> erc1155;
+//!         #[borrow]
+//!         ERC1155Supply<MyParams> supply;
+//!     }
+//! }
+//!
+//! #[external]
+//! #[inherit(ERC1155Supply<MyParams>, ERC1155<MyParams>)]
+//! impl MyEditions {}
+//! ```
+//!
+//! The hook runs before the recipient of a mint is asked to accept it, so the
+//! recipient already sees the new supply. The supplies live in the
+//! `rustmate.ERC1155Supply` namespace, see [`crate::utils::namespace`].
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use crate::tokens::erc1155::{
+    ERC1155Error,
+    ERC1155Params,
+    ERC1155,
+};
+use crate::utils::namespace;
+use alloy_primitives::{
+    b256,
+    Address,
+    B256,
+    U256,
+};
+use core::marker::PhantomData;
+use stylus_sdk::prelude::*;
+
+sol_storage! {
+    /// ERC1155Supply tracks the supply of the tokens of an [`ERC1155`]
+    pub struct ERC1155Supply<T: ERC1155Params> {
+        PhantomData<T> phantom;
+    }
+
+    /// The supplies, kept in the `rustmate.ERC1155Supply` namespace
+    pub struct SupplyStorage {
+        mapping(uint256 => uint256) total_supply;
+        uint256 total_supply_all;
+    }
+}
+
+/// keccak256(abi.encode(uint256(keccak256("rustmate.ERC1155Supply")) - 1)) & ~bytes32(uint256(0xff))
+const STORAGE_SLOT: B256 =
+    b256!("5e936090d360a6181d62302fc3458601f62e48666166f3f841910b0c7de0dd00");
+
+/// Simplifies the result type for the contract's methods.
+type Result<T, E = ERC1155Error> = core::result::Result<T, E>;
+
+impl SupplyStorage {
+    /// Returns the supplies of the token in their namespace.
+    fn load() -> Self {
+        namespace::load(STORAGE_SLOT)
+    }
+
+    fn increase_supply(&mut self, id: U256, amount: U256) {
+        let supply = self.total_supply.get(id) + amount;
+        self.total_supply.insert(id, supply);
+
+        let supply_all = self.total_supply_all.get() + amount;
+        self.total_supply_all.set(supply_all);
+    }
+
+    /// Cannot underflow, since the burned balances were checked by [`ERC1155`].
+    fn decrease_supply(&mut self, id: U256, amount: U256) {
+        let supply = self.total_supply.get(id) - amount;
+        self.total_supply.insert(id, supply);
+
+        let supply_all = self.total_supply_all.get() - amount;
+        self.total_supply_all.set(supply_all);
+    }
+}
+
+impl<T: ERC1155Params> ERC1155Supply<T> {
+    /// Adds minted amounts to, and removes burned amounts from, the supply of
+    /// their ids. Call it from [`ERC1155Params::after_token_transfer`]. Transfers
+    /// between accounts leave the supplies unchanged.
+    pub fn after_token_transfer(from: Address, to: Address, ids: &[U256], amounts: &[U256]) {
+        if !from.is_zero() && !to.is_zero() {
+            return;
+        }
+
+        let mut supply = SupplyStorage::load();
+        for (&id, &amount) in ids.iter().zip(amounts) {
+            if from.is_zero() {
+                supply.increase_supply(id, amount);
+            } else {
+                supply.decrease_supply(id, amount);
+            }
+        }
+    }
+}
+
+#[external]
+#[inherit(ERC1155<T>)]
+impl<T: ERC1155Params> ERC1155Supply<T> {
+    pub fn total_supply(&self, id: U256) -> Result<U256> {
+        Ok(SupplyStorage::load().total_supply.get(id))
+    }
+
+    #[selector(name = "totalSupply")]
+    pub fn total_supply_all(&self) -> Result<U256> {
+        Ok(SupplyStorage::load().total_supply_all.get())
+    }
+
+    pub fn exists(&self, id: U256) -> Result<bool> {
+        Ok(SupplyStorage::load().total_supply.get(id) != U256::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        Host,
+        ResultExt,
+        ALICE,
+        BOB,
+    };
+    use crate::tokens::erc1155::{
+        ERC1155InsufficientBalance,
+        LengthMismatch,
+    };
+    use alloc::{
+        string::String,
+        vec,
+        vec::Vec,
+    };
+    use core::borrow::BorrowMut;
+    use stylus_sdk::abi::Bytes;
+    use alloy_sol_types::SolError;
+
+    struct TestParams;
+
+    impl ERC1155Params for TestParams {
+        fn uri(_id: U256) -> String {
+            String::new()
+        }
+
+        fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC1155<Self>>>(
+            _storage: &mut S,
+            from: Address,
+            to: Address,
+            ids: &[U256],
+            amounts: &[U256],
+        ) -> Result<(), Vec<u8>> {
+            ERC1155Supply::<Self>::after_token_transfer(from, to, ids, amounts);
+            Ok(())
+        }
+    }
+
+    sol_storage! {
+        pub struct TestEditions {
+            #[borrow]
+            ERC1155<TestParams> erc1155;
+            #[borrow]
+            ERC1155Supply<TestParams> supply;
+        }
+    }
+
+    unsafe impl TopLevelStorage for TestEditions {}
+
+    type Token = ERC1155<TestParams>;
+
+    /// Makes `receiver` a contract answering the ERC-1155 acceptance checks with `magic_value`.
+    fn accept(host: &Host, receiver: Address, magic_value: u32) {
+        let mut word = [0u8; 32];
+        word[..4].copy_from_slice(&magic_value.to_be_bytes());
+        host.mock_call(receiver, Ok(word.to_vec()));
+    }
+
+    fn setup() -> (Host, TestEditions) {
+        let host = Host::new();
+        let mut editions = host.contract::<TestEditions>();
+        accept(&host, ALICE, 0xf23a6e61);
+        Token::mint(&mut editions, ALICE, U256::from(1), U256::from(100), Bytes(vec![]))
+            .unwrap_ok();
+        accept(&host, ALICE, 0xbc197c81);
+        Token::batch_mint(
+            &mut editions,
+            ALICE,
+            vec![U256::from(1), U256::from(2)],
+            vec![U256::from(50), U256::from(10)],
+            Bytes(vec![]),
+        )
+        .unwrap_ok();
+        (host, editions)
+    }
+
+    #[test]
+    fn mint() {
+        let (_host, editions) = setup();
+
+        assert_eq!(editions.supply.total_supply(U256::from(1)).unwrap_ok(), U256::from(150));
+        assert_eq!(editions.supply.total_supply(U256::from(2)).unwrap_ok(), U256::from(10));
+        assert_eq!(editions.supply.total_supply_all().unwrap_ok(), U256::from(160));
+        assert!(editions.supply.exists(U256::from(2)).unwrap_ok());
+        assert!(!editions.supply.exists(U256::from(3)).unwrap_ok());
+    }
+
+    #[test]
+    fn burn() {
+        let (_host, mut editions) = setup();
+
+        Token::burn(&mut editions, ALICE, U256::from(1), U256::from(30)).unwrap_ok();
+        Token::batch_burn(
+            &mut editions,
+            ALICE,
+            vec![U256::from(1), U256::from(2)],
+            vec![U256::from(20), U256::from(10)],
+        )
+        .unwrap_ok();
+
+        assert_eq!(editions.supply.total_supply(U256::from(1)).unwrap_ok(), U256::from(100));
+        assert_eq!(editions.supply.total_supply_all().unwrap_ok(), U256::from(100));
+        assert!(!editions.supply.exists(U256::from(2)).unwrap_ok());
+    }
+
+    #[test]
+    fn transfer() {
+        let (host, mut editions) = setup();
+        host.set_sender(ALICE);
+
+        Token::safe_transfer_from(
+            &mut editions,
+            ALICE,
+            BOB,
+            U256::from(1),
+            U256::from(30),
+            Bytes(vec![]),
+        )
+        .unwrap_ok();
+
+        assert_eq!(editions.supply.total_supply(U256::from(1)).unwrap_ok(), U256::from(150));
+        assert_eq!(editions.supply.total_supply_all().unwrap_ok(), U256::from(160));
+    }
+
+    #[test]
+    fn burn_too_much() {
+        let (_host, mut editions) = setup();
+
+        let revert = Token::burn(&mut editions, ALICE, U256::from(2), U256::from(11))
+            .unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ERC1155InsufficientBalance {
+                sender: ALICE,
+                balance: U256::from(10),
+                needed: U256::from(11),
+                tokenId: U256::from(2),
+            }
+            .encode()
+        );
+        assert_eq!(editions.supply.total_supply(U256::from(2)).unwrap_ok(), U256::from(10));
+    }
+
+    #[test]
+    fn batch_mint_length_mismatch() {
+        let (_host, mut editions) = setup();
+
+        let revert = Token::batch_mint(
+            &mut editions,
+            ALICE,
+            vec![U256::from(3)],
+            vec![],
+            Bytes(vec![]),
+        )
+        .unwrap_revert();
+
+        assert_eq!(revert, LengthMismatch {}.encode());
+        assert!(!editions.supply.exists(U256::from(3)).unwrap_ok());
+    }
+}
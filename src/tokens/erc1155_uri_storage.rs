@@ -0,0 +1,172 @@
+//! Provides a storage-backed URI extension for the [`ERC1155`] token.
+//!
+//! The [`ERC1155URIStorage`] type lets metadata change after deployment. `uri`
+//! returns, in order of precedence, the URI set for the id itself, the base URI,
+//! or [`ERC1155Params::uri`] when neither is set. Every change to the URI of an id
+//! emits the standard `URI` event so that marketplaces refresh their copy.
+//!
+//! As described in ERC-1155, clients replace `{id}` in a URI with the id as 64
+//! lowercase hex digits, which [`substitute_id`] does on chain when needed.
+//!
+//! [`ERC1155URIStorage`] is inherited alongside [`ERC1155`], and must come first so
+//! that its `uri` takes precedence:
+//!
+//! ```ignore
+//! sol_storage! {
+//!     #[entrypoint]
+//!     pub struct MyEditions {
+//!         #[borrow]
+//!         ERC1155<MyParams> erc1155;
+//!         #[borrow]
+//!         ERC1155URIStorage<MyParams> uri_storage;
+//!     }
+//! }
+//!
+//! #[external]
+//! #[inherit(ERC1155URIStorage<MyParams>, ERC1155<MyParams>)]
+//! impl MyEditions {}
+//! ```
+//!
+//! The URIs are only set through the inherent methods, so the inheriting contract
+//! decides who may change them.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use crate::tokens::erc1155::{
+    ERC1155Error,
+    ERC1155Params,
+    ERC1155,
+    URI,
+};
+use crate::utils::lib_string;
+use alloc::string::String;
+use alloy_primitives::U256;
+use core::marker::PhantomData;
+use stylus_sdk::{
+    evm,
+    prelude::*,
+};
+
+/// Returns `uri` with every `{id}` replaced by `id` as 64 lowercase hex digits.
+pub fn substitute_id(uri: &str, id: U256) -> String {
+    uri.replace("{id}", &lib_string::to_hex_digits(id))
+}
+
+sol_storage! {
+    /// ERC1155URIStorage stores the base URI and per-id URIs of an [`ERC1155`]
+    pub struct ERC1155URIStorage<T: ERC1155Params> {
+        string base_uri;
+        mapping(uint256 => string) token_uris;
+        PhantomData<T> phantom;
+    }
+}
+
+/// Simplifies the result type for the contract's methods.
+type Result<T, E = ERC1155Error> = core::result::Result<T, E>;
+
+impl<T: ERC1155Params> ERC1155URIStorage<T> {
+    /// Sets the URI of every id without one of its own, usually containing `{id}`.
+    /// An empty base falls back to [`ERC1155Params::uri`].
+    ///
+    /// Emits no event, since `URI` describes a single id.
+    pub fn set_base_uri(&mut self, base_uri: &str) {
+        self.base_uri.set_str(base_uri);
+    }
+
+    /// Sets the URI of `id`, overriding the base URI. An empty URI falls back to it.
+    pub fn set_uri(&mut self, id: U256, uri: &str) {
+        self.token_uris.setter(id).set_str(uri);
+
+        evm::log(URI {
+            value: self.resolve_uri(id),
+            id,
+        });
+    }
+
+    fn resolve_uri(&self, id: U256) -> String {
+        let uri = self.token_uris.getter(id).get_string();
+        if !uri.is_empty() {
+            return uri;
+        }
+
+        let base_uri = self.base_uri.get_string();
+        if !base_uri.is_empty() {
+            return base_uri;
+        }
+
+        T::uri(id)
+    }
+}
+
+#[external]
+#[inherit(ERC1155<T>)]
+impl<T: ERC1155Params> ERC1155URIStorage<T> {
+    #[selector(name = "uri")]
+    pub fn uri(&self, id: U256) -> Result<String> {
+        Ok(self.resolve_uri(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        Host,
+        ResultExt,
+    };
+
+    struct TestParams;
+
+    impl ERC1155Params for TestParams {
+        fn uri(_id: U256) -> String {
+            "ipfs://default/{id}.json".into()
+        }
+    }
+
+    type URIStorage = ERC1155URIStorage<TestParams>;
+
+    const ID: U256 = U256::from_limbs([0x4cce0, 0, 0, 0]);
+
+    #[test]
+    fn fallback_uri() {
+        let host = Host::new();
+        let uri_storage = host.contract::<URIStorage>();
+
+        assert_eq!(uri_storage.uri(ID).unwrap_ok(), "ipfs://default/{id}.json");
+    }
+
+    #[test]
+    fn base_and_token_uri() {
+        let host = Host::new();
+        let mut uri_storage = host.contract::<URIStorage>();
+
+        uri_storage.set_base_uri("https://token-cdn-domain/{id}.json");
+        assert_eq!(uri_storage.uri(ID).unwrap_ok(), "https://token-cdn-domain/{id}.json");
+
+        uri_storage.set_uri(ID, "ipfs://edition.json");
+        assert_eq!(uri_storage.uri(ID).unwrap_ok(), "ipfs://edition.json");
+        assert!(host.emitted(&URI {
+            value: "ipfs://edition.json".into(),
+            id: ID,
+        }));
+
+        uri_storage.set_uri(ID, "");
+        assert_eq!(uri_storage.uri(ID).unwrap_ok(), "https://token-cdn-domain/{id}.json");
+        assert!(host.emitted(&URI {
+            value: "https://token-cdn-domain/{id}.json".into(),
+            id: ID,
+        }));
+    }
+
+    // The example from ERC-1155.
+    #[test]
+    fn id_substitution() {
+        assert_eq!(
+            substitute_id("https://token-cdn-domain/{id}.json", ID),
+            concat!(
+                "https://token-cdn-domain/",
+                "000000000000000000000000000000000000000000000000000000000004cce0.json",
+            )
+        );
+    }
+}
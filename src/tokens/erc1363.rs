@@ -0,0 +1,380 @@
+//! Provides an implementation of the ERC-1363 payable token standard.
+//!
+//! The [`ERC1363`] type extends [`ERC20`] with `transferAndCall`,
+//! `transferFromAndCall` and `approveAndCall`, which notify the recipient
+//! or spender contract in the same transaction. It is inherited alongside
+//! [`ERC20`]:
+//!
+//! ```ignore
+//! sol_storage! {
+//!     #[entrypoint]
+//!     pub struct MyToken {
+//!         #[borrow]
+//!         ERC20<MyParams> erc20;
+//!         #[borrow]
+//!         ERC1363<MyParams> erc1363;
+//!     }
+//! }
+//!
+//! #[external]
+//! #[inherit(ERC1363<MyParams>, ERC20<MyParams>)]
+//! impl MyToken {}
+//! ```
+//!
+//! Tokens move through the [`ERC20`] methods, so the [`ERC20Params`] transfer
+//! hooks, and extensions such as `ERC20Votes` built on them, see every transfer.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use crate::host::msg;
+use crate::tokens::erc20::{
+    ERC20Error,
+    ERC20Params,
+    ERC20,
+};
+use alloc::vec::Vec;
+use alloy_primitives::{
+    Address,
+    U256,
+};
+use alloy_sol_types::{
+    sol,
+    SolError,
+};
+use core::{
+    borrow::BorrowMut,
+    marker::PhantomData,
+};
+use stylus_sdk::{
+    abi::Bytes,
+    prelude::*,
+};
+
+sol_storage! {
+    /// ERC1363 implements the ERC-1363 methods on top of [`ERC20`]
+    pub struct ERC1363<T: ERC20Params> {
+        PhantomData<T> phantom;
+    }
+}
+
+// Declare Solidity error types
+sol! {
+    error ERC1363InvalidReceiver(address receiver);
+    error ERC1363InvalidSpender(address spender);
+}
+
+/// Represents the ways methods may fail.
+pub enum ERC1363Error {
+    ERC1363InvalidReceiver(ERC1363InvalidReceiver),
+    ERC1363InvalidSpender(ERC1363InvalidSpender),
+    ERC20(ERC20Error),
+    CallFailed(stylus_sdk::call::Error),
+}
+
+impl From<ERC20Error> for ERC1363Error {
+    fn from(err: ERC20Error) -> Self {
+        Self::ERC20(err)
+    }
+}
+
+impl From<stylus_sdk::call::Error> for ERC1363Error {
+    fn from(err: stylus_sdk::call::Error) -> Self {
+        Self::CallFailed(err)
+    }
+}
+
+/// We will soon provide a `#[derive(SolidityError)]` to clean this up.
+impl From<ERC1363Error> for Vec<u8> {
+    fn from(val: ERC1363Error) -> Self {
+        match val {
+            ERC1363Error::ERC1363InvalidReceiver(err) => err.encode(),
+            ERC1363Error::ERC1363InvalidSpender(err) => err.encode(),
+            ERC1363Error::ERC20(err) => err.into(),
+            ERC1363Error::CallFailed(err) => err.into(),
+        }
+    }
+}
+
+/// Simplifies the result type for the contract's methods.
+type Result<T, E = ERC1363Error> = core::result::Result<T, E>;
+
+impl<T: ERC20Params> ERC1363<T> {
+    fn call_receiver<S: TopLevelStorage>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        value: U256,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        // ERC-1363 requires recipients to be contracts.
+        if !to.has_code() {
+            return Err(ERC1363Error::ERC1363InvalidReceiver(
+                ERC1363InvalidReceiver { receiver: to },
+            ));
+        }
+
+        let receiver = IERC1363Receiver::new(to);
+        let received = receiver
+            .on_transfer_received(&mut *storage, msg::sender(), from, value, data)?
+            .0;
+
+        // 0x88a7ca5c = bytes4(keccak256("onTransferReceived(address,address,uint256,bytes)"))
+        if u32::from_be_bytes(received) != 0x88a7ca5c {
+            return Err(ERC1363Error::ERC1363InvalidReceiver(
+                ERC1363InvalidReceiver { receiver: to },
+            ));
+        }
+        Ok(())
+    }
+
+    fn call_spender<S: TopLevelStorage>(
+        storage: &mut S,
+        spender: Address,
+        value: U256,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        if !spender.has_code() {
+            return Err(ERC1363Error::ERC1363InvalidSpender(ERC1363InvalidSpender {
+                spender,
+            }));
+        }
+
+        let receiver = IERC1363Spender::new(spender);
+        let received = receiver
+            .on_approval_received(&mut *storage, msg::sender(), value, data)?
+            .0;
+
+        // 0x7b04a2d0 = bytes4(keccak256("onApprovalReceived(address,uint256,bytes)"))
+        if u32::from_be_bytes(received) != 0x7b04a2d0 {
+            return Err(ERC1363Error::ERC1363InvalidSpender(ERC1363InvalidSpender {
+                spender,
+            }));
+        }
+        Ok(())
+    }
+}
+
+#[external]
+#[inherit(ERC20<T>)]
+impl<T: ERC20Params> ERC1363<T> {
+    pub fn transfer_and_call<S>(storage: &mut S, to: Address, value: U256) -> Result<bool>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC20<T>>,
+    {
+        Self::transfer_and_call_with_data(storage, to, value, Bytes(vec![]))
+    }
+
+    #[selector(name = "transferAndCall")]
+    pub fn transfer_and_call_with_data<S>(
+        storage: &mut S,
+        to: Address,
+        value: U256,
+        data: Bytes,
+    ) -> Result<bool>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC20<T>>,
+    {
+        ERC20::<T>::transfer(storage, to, value)?;
+        Self::call_receiver(storage, msg::sender(), to, value, data.0)?;
+        Ok(true)
+    }
+
+    pub fn transfer_from_and_call<S>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        value: U256,
+    ) -> Result<bool>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC20<T>>,
+    {
+        Self::transfer_from_and_call_with_data(storage, from, to, value, Bytes(vec![]))
+    }
+
+    #[selector(name = "transferFromAndCall")]
+    pub fn transfer_from_and_call_with_data<S>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        value: U256,
+        data: Bytes,
+    ) -> Result<bool>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC20<T>>,
+    {
+        ERC20::<T>::transfer_from(storage, from, to, value)?;
+        Self::call_receiver(storage, from, to, value, data.0)?;
+        Ok(true)
+    }
+
+    pub fn approve_and_call<S>(storage: &mut S, spender: Address, value: U256) -> Result<bool>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC20<T>>,
+    {
+        Self::approve_and_call_with_data(storage, spender, value, Bytes(vec![]))
+    }
+
+    #[selector(name = "approveAndCall")]
+    pub fn approve_and_call_with_data<S>(
+        storage: &mut S,
+        spender: Address,
+        value: U256,
+        data: Bytes,
+    ) -> Result<bool>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC20<T>>,
+    {
+        BorrowMut::<ERC20<T>>::borrow_mut(storage).approve(spender, value)?;
+        Self::call_spender(storage, spender, value, data.0)?;
+        Ok(true)
+    }
+
+    pub fn supports_interface(interface: [u8; 4]) -> Result<bool> {
+        let supported = interface == 0x01ffc9a7u32.to_be_bytes() // ERC165 Interface ID for ERC165
+            || interface == 0xb0202a11u32.to_be_bytes(); // ERC165 Interface ID for ERC1363
+        Ok(supported)
+    }
+}
+
+sol_interface! {
+    interface IERC1363Receiver {
+        function onTransferReceived(address operator, address from, uint256 value, bytes data) external returns(bytes4);
+    }
+
+    interface IERC1363Spender {
+        function onApprovalReceived(address owner, uint256 value, bytes data) external returns(bytes4);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        Host,
+        ResultExt,
+        ALICE,
+        BOB,
+    };
+    use alloy_primitives::{
+        address,
+        B256,
+    };
+
+    struct TestParams;
+
+    impl ERC20Params for TestParams {
+        const NAME: &'static str = "Payable Token";
+        const SYMBOL: &'static str = "PAY";
+        const DECIMALS: u8 = 18;
+        const INITIAL_CHAIN_ID: u64 = 1;
+        const INITIAL_DOMAIN_SEPARATOR: B256 = B256::ZERO;
+    }
+
+    sol_storage! {
+        pub struct TestToken {
+            #[borrow]
+            ERC20<TestParams> erc20;
+            #[borrow]
+            ERC1363<TestParams> erc1363;
+        }
+    }
+
+    unsafe impl TopLevelStorage for TestToken {}
+
+    type Token = ERC1363<TestParams>;
+
+    const RECEIVER: Address = address!("000000000000000000000000000000000000cafe");
+
+    /// Encodes a `bytes4` return value.
+    fn selector(selector: u32) -> Vec<u8> {
+        let mut word = vec![0; 32];
+        word[..4].copy_from_slice(&selector.to_be_bytes());
+        word
+    }
+
+    fn setup() -> (Host, TestToken) {
+        let host = Host::new();
+        let mut token = host.contract::<TestToken>();
+        ERC20::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+        host.set_sender(ALICE);
+        (host, token)
+    }
+
+    #[test]
+    fn transfer_and_call() {
+        let (host, mut token) = setup();
+
+        host.mock_call(RECEIVER, Ok(selector(0x88a7ca5c)));
+
+        assert!(Token::transfer_and_call(&mut token, RECEIVER, U256::from(1_000)).unwrap_ok());
+        assert_eq!(token.erc20.balance_of(RECEIVER).unwrap_ok(), U256::from(1_000));
+    }
+
+    #[test]
+    fn transfer_from_and_call() {
+        let (host, mut token) = setup();
+
+        host.mock_call(RECEIVER, Ok(selector(0x88a7ca5c)));
+        token.erc20.approve(BOB, U256::from(1_000)).unwrap_ok();
+
+        host.set_sender(BOB);
+        let data = Bytes(vec![1, 2, 3]);
+        Token::transfer_from_and_call_with_data(&mut token, ALICE, RECEIVER, U256::from(1_000), data)
+            .unwrap_ok();
+
+        assert_eq!(token.erc20.balance_of(RECEIVER).unwrap_ok(), U256::from(1_000));
+    }
+
+    #[test]
+    fn transfer_and_call_to_account_without_code() {
+        let (_host, mut token) = setup();
+
+        let revert = Token::transfer_and_call(&mut token, BOB, U256::from(1_000)).unwrap_revert();
+
+        assert_eq!(revert, ERC1363InvalidReceiver { receiver: BOB }.encode());
+    }
+
+    #[test]
+    fn transfer_and_call_wrong_return_value() {
+        let (host, mut token) = setup();
+
+        host.mock_call(RECEIVER, Ok(selector(0x150b7a02)));
+
+        let revert =
+            Token::transfer_and_call(&mut token, RECEIVER, U256::from(1_000)).unwrap_revert();
+
+        assert_eq!(revert, ERC1363InvalidReceiver { receiver: RECEIVER }.encode());
+    }
+
+    #[test]
+    fn approve_and_call() {
+        let (host, mut token) = setup();
+
+        host.mock_call(RECEIVER, Ok(selector(0x7b04a2d0)));
+
+        assert!(Token::approve_and_call(&mut token, RECEIVER, U256::from(1_000)).unwrap_ok());
+        assert_eq!(
+            token.erc20.allowance(ALICE, RECEIVER).unwrap_ok(),
+            U256::from(1_000)
+        );
+    }
+
+    #[test]
+    fn approve_and_call_wrong_return_value() {
+        let (host, mut token) = setup();
+
+        host.mock_call(RECEIVER, Ok(selector(0x88a7ca5c)));
+
+        let revert =
+            Token::approve_and_call(&mut token, RECEIVER, U256::from(1_000)).unwrap_revert();
+
+        assert_eq!(revert, ERC1363InvalidSpender { spender: RECEIVER }.encode());
+    }
+
+    #[test]
+    fn supports_interface() {
+        assert!(Token::supports_interface(0x01ffc9a7u32.to_be_bytes()).unwrap_ok());
+        assert!(Token::supports_interface(0xb0202a11u32.to_be_bytes()).unwrap_ok());
+        assert!(!Token::supports_interface(0xffffffffu32.to_be_bytes()).unwrap_ok());
+    }
+}
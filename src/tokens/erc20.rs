@@ -4,38 +4,55 @@
 //! and is intended to be inherited by other contract types.
 //!
 //! You can configure the behavior of [`ERC20`] via the [`ERC20Params`] trait,
-//! which allows specifying the name, symbol, and token uri.
+//! which allows specifying the name, symbol, and token uri, whether that
+//! metadata is instead set at runtime, the ERC-3156 flash mint terms, and
+//! hooking into every balance change.
 //!
 //! Note that this code is unaudited and not fit for production use.
 
+use crate::host::{
+    block,
+    contract,
+    msg,
+};
+use crate::utils::ecdsa::{
+    self,
+    ECDSAError,
+};
+use crate::utils::eip712::{
+    self,
+    Domain,
+    EIP712DomainChanged,
+    EIP712,
+};
+use crate::utils::signature_checker;
 use alloc::{
     string::String,
     vec::Vec,
 };
 use alloy_primitives::{
-    address,
     Address,
+    FixedBytes,
     B256,
     U256,
+    U8,
 };
 use alloy_sol_types::{
     sol,
-    sol_data,
     SolError,
-    SolType,
 };
-use core::marker::PhantomData;
-use stylus_sdk::call::RawCall;
+use core::{
+    borrow::BorrowMut,
+    marker::PhantomData,
+};
 use stylus_sdk::crypto;
 use stylus_sdk::{
-    block,
-    contract,
+    abi::Bytes,
     evm,
-    msg,
     prelude::*,
 };
 
-pub trait ERC20Params {
+pub trait ERC20Params: Sized {
     const NAME: &'static str;
 
     const SYMBOL: &'static str;
@@ -46,15 +63,73 @@ pub trait ERC20Params {
     const INITIAL_CHAIN_ID: u64;
 
     const INITIAL_DOMAIN_SEPARATOR: B256;
+
+    /// Whether the name, symbol and decimals are set once through `initialize`
+    /// instead of the constants above, so that one program can back many tokens.
+    /// The domain separator is then always computed from the stored name.
+    const RUNTIME_METADATA: bool = false;
+
+    /// Whether the token offers ERC-3156 flash mints of itself.
+    const FLASH_MINT: bool = false;
+
+    /// The flash mint fee, in basis points of the amount borrowed.
+    const FLASH_FEE_BPS: u64 = 0;
+
+    /// The account flash mint fees are paid to, or zero to burn them.
+    const FLASH_FEE_RECEIVER: Address = Address::ZERO;
+
+    /// Called before `amount` tokens move from `from` to `to`, where `from` is zero
+    /// for a mint and `to` is zero for a burn. `storage` is the contract inheriting
+    /// [`ERC20`]. Returning `Err` reverts the transfer with the given data.
+    fn before_token_transfer<S: TopLevelStorage + BorrowMut<ERC20<Self>>>(
+        _storage: &mut S,
+        _from: Address,
+        _to: Address,
+        _amount: U256,
+    ) -> Result<(), Vec<u8>> {
+        Ok(())
+    }
+
+    /// Called after `amount` tokens have moved from `from` to `to`, with the same
+    /// arguments as [`ERC20Params::before_token_transfer`].
+    fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC20<Self>>>(
+        _storage: &mut S,
+        _from: Address,
+        _to: Address,
+        _amount: U256,
+    ) -> Result<(), Vec<u8>> {
+        Ok(())
+    }
+
+    /// Called before `initialize` sets the runtime metadata. `storage` is the
+    /// contract inheriting [`ERC20`]. Returning `Err` reverts the call with the
+    /// given data. The default lets anyone initialize the token, see
+    /// [`ERC20::initialize`].
+    fn before_initialize<S: TopLevelStorage + BorrowMut<ERC20<Self>>>(
+        _storage: &mut S,
+    ) -> Result<(), Vec<u8>> {
+        Ok(())
+    }
 }
 
 sol_storage! {
     /// ERC20 implements all ERC-20 methods
+    ///
+    /// The EIP-3009 authorization states, the runtime metadata and the cached
+    /// EIP-712 domain follow the original four fields. ERC20 therefore takes more
+    /// slots than in earlier versions, which moves any field declared after it:
+    /// check the layout before upgrading a deployed contract.
     pub struct ERC20<T: ERC20Params> {
         uint256 total_supply;
         mapping(address => uint256) balance;
         mapping(address => mapping(address => uint256)) allowance;
         mapping(address => uint256) nonces;
+        mapping(address => mapping(bytes32 => bool)) authorization_state;
+        bool initialized;
+        string name;
+        string symbol;
+        uint8 decimals;
+        EIP712 eip712;
         PhantomData<T> phantom;
     }
 }
@@ -63,15 +138,85 @@ sol_storage! {
 sol! {
     event Transfer(address indexed from, address indexed to, uint256 amount);
     event Approval(address indexed owner, address indexed spender, uint256 amount);
+    event AuthorizationUsed(address indexed authorizer, bytes32 indexed nonce);
+    event AuthorizationCanceled(address indexed authorizer, bytes32 indexed nonce);
 
     error PermitDeadlineExpired();
     error InvalidSigner();
+    error InvalidSignatureS(bytes32 s);
+    error ERC20InsufficientBalance(address sender, uint256 balance, uint256 needed);
+    error ERC20InsufficientAllowance(address spender, uint256 allowance, uint256 needed);
+    error ERC3156UnsupportedToken(address token);
+    error ERC3156ExceededMaxLoan(uint256 maxLoan);
+    error ERC3156InvalidReceiver(address receiver);
+    error ERC3156FeeOverflow(uint256 amount);
+    error AuthorizationNotYetValid();
+    error AuthorizationExpired();
+    error AuthorizationUsedOrCanceled();
+    error CallerNotPayee();
+    error AlreadyInitialized();
+    error MetadataIsConstant();
+}
+
+// Declare the EIP-712 messages signed for this token
+sol! {
+    struct Permit {
+        address owner;
+        address spender;
+        uint256 value;
+        uint256 nonce;
+        uint256 deadline;
+    }
+
+    struct TransferWithAuthorization {
+        address from;
+        address to;
+        uint256 value;
+        uint256 validAfter;
+        uint256 validBefore;
+        bytes32 nonce;
+    }
+
+    struct ReceiveWithAuthorization {
+        address from;
+        address to;
+        uint256 value;
+        uint256 validAfter;
+        uint256 validBefore;
+        bytes32 nonce;
+    }
+
+    struct CancelAuthorization {
+        address authorizer;
+        bytes32 nonce;
+    }
 }
 
 /// Represents the ways methods may fail.
 pub enum ERC20Error {
     PermitDeadlineExpired(PermitDeadlineExpired),
     InvalidSigner(InvalidSigner),
+    InvalidSignatureS(InvalidSignatureS),
+    ERC20InsufficientBalance(ERC20InsufficientBalance),
+    ERC20InsufficientAllowance(ERC20InsufficientAllowance),
+    ERC3156UnsupportedToken(ERC3156UnsupportedToken),
+    ERC3156ExceededMaxLoan(ERC3156ExceededMaxLoan),
+    ERC3156InvalidReceiver(ERC3156InvalidReceiver),
+    ERC3156FeeOverflow(ERC3156FeeOverflow),
+    AuthorizationNotYetValid(AuthorizationNotYetValid),
+    AuthorizationExpired(AuthorizationExpired),
+    AuthorizationUsedOrCanceled(AuthorizationUsedOrCanceled),
+    CallerNotPayee(CallerNotPayee),
+    AlreadyInitialized(AlreadyInitialized),
+    MetadataIsConstant(MetadataIsConstant),
+    HookFailed(Vec<u8>),
+    CallFailed(stylus_sdk::call::Error),
+}
+
+impl From<stylus_sdk::call::Error> for ERC20Error {
+    fn from(err: stylus_sdk::call::Error) -> Self {
+        Self::CallFailed(err)
+    }
 }
 
 /// We will soon provide a `#[derive(SolidityError)]` to clean this up.
@@ -80,6 +225,21 @@ impl From<ERC20Error> for Vec<u8> {
         match val {
             ERC20Error::PermitDeadlineExpired(err) => err.encode(),
             ERC20Error::InvalidSigner(err) => err.encode(),
+            ERC20Error::InvalidSignatureS(err) => err.encode(),
+            ERC20Error::ERC20InsufficientBalance(err) => err.encode(),
+            ERC20Error::ERC20InsufficientAllowance(err) => err.encode(),
+            ERC20Error::ERC3156UnsupportedToken(err) => err.encode(),
+            ERC20Error::ERC3156ExceededMaxLoan(err) => err.encode(),
+            ERC20Error::ERC3156InvalidReceiver(err) => err.encode(),
+            ERC20Error::ERC3156FeeOverflow(err) => err.encode(),
+            ERC20Error::AuthorizationNotYetValid(err) => err.encode(),
+            ERC20Error::AuthorizationExpired(err) => err.encode(),
+            ERC20Error::AuthorizationUsedOrCanceled(err) => err.encode(),
+            ERC20Error::CallerNotPayee(err) => err.encode(),
+            ERC20Error::AlreadyInitialized(err) => err.encode(),
+            ERC20Error::MetadataIsConstant(err) => err.encode(),
+            ERC20Error::HookFailed(err) => err,
+            ERC20Error::CallFailed(err) => err.into(),
         }
     }
 }
@@ -88,21 +248,59 @@ impl From<ERC20Error> for Vec<u8> {
 type Result<T, E = ERC20Error> = core::result::Result<T, E>;
 
 impl<T: ERC20Params> ERC20<T> {
-    pub fn compute_domain_separator() -> Result<B256> {
-        let mut digest_input = [0u8; 160];
-        digest_input[0..32].copy_from_slice(&crypto::keccak("EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)".as_bytes())[..]);
-        digest_input[32..64].copy_from_slice(&crypto::keccak(T::NAME.as_bytes())[..]);
-        digest_input[64..96].copy_from_slice(&crypto::keccak("1".as_bytes())[..]);
-        digest_input[96..128].copy_from_slice(&block::chainid().to_be_bytes()[..]);
-        digest_input[128..160].copy_from_slice(&contract::address()[..]);
+    /// Returns the EIP-712 domain of this token, named after the token.
+    pub fn domain(&self) -> Result<Domain> {
+        Ok(Domain::new(self.name()?, "1".into()))
+    }
+
+    pub fn compute_domain_separator(&self) -> Result<B256> {
+        Ok(self.domain()?.separator())
+    }
+
+    fn before_token_transfer<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        amount: U256,
+    ) -> Result<()> {
+        T::before_token_transfer(storage, from, to, amount).map_err(ERC20Error::HookFailed)
+    }
+
+    fn after_token_transfer<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        amount: U256,
+    ) -> Result<()> {
+        T::after_token_transfer(storage, from, to, amount).map_err(ERC20Error::HookFailed)
+    }
+
+    /// Returns the EIP-712 digest of `struct_hash` under this token's domain.
+    pub fn hash_typed_data(&self, struct_hash: B256) -> Result<B256> {
+        Ok(eip712::hash_typed_data(self.domain_separator()?, struct_hash))
+    }
 
-        Ok(crypto::keccak(digest_input))
+    /// Recovers the signer of `digest`, rejecting malleable signatures.
+    pub fn recover(digest: B256, v: u8, r: B256, s: B256) -> Result<Address> {
+        ecdsa::recover(digest, v, r, s).map_err(|err| match err {
+            ECDSAError::InvalidSignatureS(err) => {
+                ERC20Error::InvalidSignatureS(InvalidSignatureS { s: err.s })
+            }
+            _ => ERC20Error::InvalidSigner(InvalidSigner {}),
+        })
     }
 
-    pub fn mint(&mut self, to: Address, amount: U256) {
-        self.total_supply.set(self.total_supply.get() + amount);
+    pub fn mint<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        to: Address,
+        amount: U256,
+    ) -> Result<()> {
+        Self::before_token_transfer(storage, Address::ZERO, to, amount)?;
+
+        let token = storage.borrow_mut();
+        token.total_supply.set(token.total_supply.get() + amount);
 
-        let mut balance_setter = self.balance.setter(to);
+        let mut balance_setter = token.balance.setter(to);
         let balance = balance_setter.get();
         balance_setter.set(balance + amount);
 
@@ -111,35 +309,218 @@ impl<T: ERC20Params> ERC20<T> {
             to,
             amount,
         });
+
+        Self::after_token_transfer(storage, Address::ZERO, to, amount)
     }
 
-    pub fn burn(&mut self, from: Address, amount: U256) {
-        let mut balance_setter = self.balance.setter(from);
+    pub fn burn<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        amount: U256,
+    ) -> Result<()> {
+        Self::before_token_transfer(storage, from, Address::ZERO, amount)?;
+
+        let token = storage.borrow_mut();
+        let mut balance_setter = token.balance.setter(from);
         let balance = balance_setter.get();
+
+        if balance < amount {
+            return Err(ERC20Error::ERC20InsufficientBalance(
+                ERC20InsufficientBalance {
+                    sender: from,
+                    balance,
+                    needed: amount,
+                },
+            ));
+        }
+
         balance_setter.set(balance - amount);
 
-        self.total_supply.set(self.total_supply.get() - amount);
+        token.total_supply.set(token.total_supply.get() - amount);
 
         evm::log(Transfer {
             from,
             to: Address::ZERO,
             amount,
         });
+
+        Self::after_token_transfer(storage, from, Address::ZERO, amount)
+    }
+
+    /// Deducts `amount` from the allowance `owner` gave `spender`, unless it is infinite.
+    fn spend_allowance(&mut self, owner: Address, spender: Address, amount: U256) -> Result<()> {
+        let allowed = self.allowance.getter(owner).get(spender);
+
+        if allowed != U256::MAX {
+            if allowed < amount {
+                return Err(ERC20Error::ERC20InsufficientAllowance(
+                    ERC20InsufficientAllowance {
+                        spender,
+                        allowance: allowed,
+                        needed: amount,
+                    },
+                ));
+            }
+
+            self.allowance.setter(owner).insert(spender, allowed - amount);
+        }
+
+        Ok(())
+    }
+
+    /// Marks the EIP-3009 authorization `nonce` of `authorizer` as used.
+    fn use_authorization(&mut self, authorizer: Address, nonce: B256) -> Result<()> {
+        let mut state = self.authorization_state.setter(authorizer);
+
+        if state.get(nonce) {
+            return Err(ERC20Error::AuthorizationUsedOrCanceled(
+                AuthorizationUsedOrCanceled {},
+            ));
+        }
+
+        state.insert(nonce, true);
+
+        evm::log(AuthorizationUsed {
+            authorizer,
+            nonce: *nonce,
+        });
+
+        Ok(())
+    }
+
+    /// Executes an EIP-3009 transfer signed by `from` over `struct_hash`, through the
+    /// same hooked path as `transfer`.
+    #[allow(clippy::too_many_arguments)]
+    fn transfer_with_signed_authorization<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        struct_hash: B256,
+        from: Address,
+        to: Address,
+        value: U256,
+        valid_after: U256,
+        valid_before: U256,
+        nonce: B256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> Result<()> {
+        let now = U256::from(block::timestamp());
+
+        if now <= valid_after {
+            return Err(ERC20Error::AuthorizationNotYetValid(AuthorizationNotYetValid {}));
+        }
+
+        if now >= valid_before {
+            return Err(ERC20Error::AuthorizationExpired(AuthorizationExpired {}));
+        }
+
+        let token = storage.borrow_mut();
+        let digest = token.hash_typed_data(struct_hash)?;
+
+        if Self::recover(digest, v, r, s)? != from {
+            return Err(ERC20Error::InvalidSigner(InvalidSigner {}));
+        }
+
+        token.use_authorization(from, nonce)?;
+
+        Self::transfer_tokens(storage, from, to, value)
+    }
+
+    /// Moves `amount` tokens between two accounts, running the transfer hooks.
+    fn transfer_tokens<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        amount: U256,
+    ) -> Result<()> {
+        Self::before_token_transfer(storage, from, to, amount)?;
+
+        let token = storage.borrow_mut();
+        let mut from_setter = token.balance.setter(from);
+        let from_balance = from_setter.get();
+
+        if from_balance < amount {
+            return Err(ERC20Error::ERC20InsufficientBalance(
+                ERC20InsufficientBalance {
+                    sender: from,
+                    balance: from_balance,
+                    needed: amount,
+                },
+            ));
+        }
+
+        from_setter.set(from_balance - amount);
+
+        let mut to_setter = token.balance.setter(to);
+        let to_balance = to_setter.get();
+        to_setter.set(to_balance + amount);
+
+        evm::log(Transfer { from, to, amount });
+
+        Self::after_token_transfer(storage, from, to, amount)
     }
 }
 
 #[external]
 impl<T: ERC20Params> ERC20<T> {
-    pub fn name() -> Result<String> {
-        Ok(T::NAME.into())
+    /// Sets the metadata of a token using [`ERC20Params::RUNTIME_METADATA`].
+    /// It can only be called once, and never for tokens with constant metadata.
+    ///
+    /// # Security
+    ///
+    /// **Whoever calls `initialize` first decides the name, symbol and decimals.**
+    /// A clone or proxy deployed in one transaction and initialized in another can
+    /// be front-run, and by default any caller is accepted. Restrict the caller in
+    /// [`ERC20Params::before_initialize`], for instance to the factory, and have
+    /// the factory deploy and initialize the token in the same transaction.
+    pub fn initialize<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        name: String,
+        symbol: String,
+        decimals: u8,
+    ) -> Result<()> {
+        if !T::RUNTIME_METADATA {
+            return Err(ERC20Error::MetadataIsConstant(MetadataIsConstant {}));
+        }
+
+        if storage.borrow_mut().initialized.get() {
+            return Err(ERC20Error::AlreadyInitialized(AlreadyInitialized {}));
+        }
+
+        T::before_initialize(storage).map_err(ERC20Error::HookFailed)?;
+
+        let token = storage.borrow_mut();
+        token.initialized.set(true);
+        token.name.set_str(name);
+        token.symbol.set_str(symbol);
+        token.decimals.set(U8::from(decimals));
+
+        let domain = token.domain()?;
+        token.eip712.cache(&domain);
+        evm::log(EIP712DomainChanged {});
+
+        Ok(())
     }
 
-    pub fn symbol() -> Result<String> {
-        Ok(T::SYMBOL.into())
+    pub fn name(&self) -> Result<String> {
+        match T::RUNTIME_METADATA {
+            true => Ok(self.name.get_string()),
+            false => Ok(T::NAME.into()),
+        }
     }
 
-    pub fn decimals() -> Result<u8> {
-        Ok(T::DECIMALS)
+    pub fn symbol(&self) -> Result<String> {
+        match T::RUNTIME_METADATA {
+            true => Ok(self.symbol.get_string()),
+            false => Ok(T::SYMBOL.into()),
+        }
+    }
+
+    pub fn decimals(&self) -> Result<u8> {
+        match T::RUNTIME_METADATA {
+            true => Ok(self.decimals.get().to()),
+            false => Ok(T::DECIMALS),
+        }
     }
 
     pub fn total_supply(&self) -> Result<U256> {
@@ -170,98 +551,86 @@ impl<T: ERC20Params> ERC20<T> {
         Ok(true)
     }
 
-    pub fn transfer(&mut self, to: Address, amount: U256) -> Result<bool> {
-        let mut from_setter = self.balance.setter(msg::sender());
-        let from_balance = from_setter.get();
-        from_setter.set(from_balance - amount);
-
-        let mut to_setter = self.balance.setter(to);
-        let to_balance = to_setter.get();
-        to_setter.set(to_balance + amount);
-
-        evm::log(Transfer {
-            from: msg::sender(),
-            to,
-            amount,
-        });
+    pub fn transfer<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        to: Address,
+        amount: U256,
+    ) -> Result<bool> {
+        Self::transfer_tokens(storage, msg::sender(), to, amount)?;
 
         Ok(true)
     }
 
-    pub fn transfer_from(&mut self, from: Address, to: Address, amount: U256) -> Result<bool> {
-        let allowed = self.allowance.getter(from).get(msg::sender());
-
-        if allowed != U256::MAX {
-            self.allowance
-                .setter(from)
-                .insert(msg::sender(), allowed - amount);
-        }
-
-        let mut from_setter = self.balance.setter(from);
-        let from_balance = from_setter.get();
-        from_setter.set(from_balance - amount);
-
-        let mut to_setter = self.balance.setter(to);
-        let to_balance = to_setter.get();
-        to_setter.set(to_balance + amount);
+    pub fn transfer_from<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        amount: U256,
+    ) -> Result<bool> {
+        storage
+            .borrow_mut()
+            .spend_allowance(from, msg::sender(), amount)?;
 
-        evm::log(Transfer { from, to, amount });
+        Self::transfer_tokens(storage, from, to, amount)?;
 
         Ok(true)
     }
 
-    pub fn permit(
-        &mut self,
+    /// Approves `spender` with a signature by `owner`, which may be a smart
+    /// contract wallet verifying it through ERC-1271.
+    #[allow(clippy::too_many_arguments)]
+    pub fn permit<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
         owner: Address,
         spender: Address,
         value: U256,
         deadline: U256,
         v: u8,
-        r: U256,
-        s: U256,
+        r: B256,
+        s: B256,
     ) -> Result<()> {
         if deadline < U256::from(block::timestamp()) {
             return Err(ERC20Error::PermitDeadlineExpired(PermitDeadlineExpired {}));
         }
 
-        let mut nonce_setter = self.balance.setter(owner);
+        let token = storage.borrow_mut();
+        let mut nonce_setter = token.nonces.setter(owner);
         let nonce = nonce_setter.get();
         nonce_setter.set(nonce + U256::from(1));
 
-        let mut struct_hash = [0u8; 192];
-        struct_hash[0..32].copy_from_slice(&crypto::keccak(b"Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)")[..]);
-        struct_hash[32..64].copy_from_slice(&owner[..]);
-        struct_hash[64..96].copy_from_slice(&spender[..]);
-        struct_hash[96..128].copy_from_slice(&value.to_be_bytes_vec()[..]);
-        // TODO: Increase nonce
-        struct_hash[128..160].copy_from_slice(&nonce.to_be_bytes_vec()[..]);
-        struct_hash[160..192].copy_from_slice(&deadline.to_be_bytes_vec()[..]);
-
-        let mut digest_input = [0u8; 2 + 32 + 32];
-        digest_input[0] = 0x19;
-        digest_input[1] = 0x01;
-        digest_input[2..34].copy_from_slice(&self.domain_separator()?[..]);
-        digest_input[34..66].copy_from_slice(&crypto::keccak(struct_hash)[..]);
-
-        let data = <sol! { (bytes32, uint8, uint256, uint256) }>::encode(&(
-            *crypto::keccak(digest_input),
-            v,
-            r,
-            s,
-        ));
+        let struct_hash = eip712::hash_struct(&Permit {
+            owner,
+            spender,
+            value,
+            nonce,
+            deadline,
+        });
 
-        let recovered_address = RawCall::new_static()
-            .gas(evm::gas_left())
-            .call(address!("0000000000000000000000000000000000000001"), &data)
-            .map(|ret| sol_data::Address::decode_single(ret.as_slice(), false).unwrap())
-            .map_err(|_| ERC20Error::InvalidSigner(InvalidSigner {}))?;
+        let digest = token.hash_typed_data(struct_hash)?;
 
-        if recovered_address.is_zero() || recovered_address != owner {
+        // Contract wallets only answer through ERC-1271, so their rejections are
+        // not mistaken for malformed ECDSA signatures.
+        let valid = match owner.has_code() {
+            true => {
+                let signature = [&r[..], &s[..], &[v]].concat();
+                signature_checker::is_valid_erc1271_signature_now(
+                    &*storage,
+                    owner,
+                    digest,
+                    &signature,
+                )
+            }
+            false => Self::recover(digest, v, r, s)? == owner,
+        };
+
+        if !valid {
             return Err(ERC20Error::InvalidSigner(InvalidSigner {}));
         }
 
-        self.allowance
-            .setter(recovered_address)
+        storage
+            .borrow_mut()
+            .allowance
+            .setter(owner)
             .insert(spender, value);
 
         evm::log(Approval {
@@ -273,11 +642,1310 @@ impl<T: ERC20Params> ERC20<T> {
         Ok(())
     }
 
-    pub fn domain_separator(&mut self) -> Result<B256> {
-        if block::chainid() == T::INITIAL_CHAIN_ID {
-            Ok(T::INITIAL_DOMAIN_SEPARATOR)
+    #[selector(name = "DOMAIN_SEPARATOR")]
+    pub fn domain_separator(&self) -> Result<B256> {
+        let cached = match T::RUNTIME_METADATA {
+            true => self.eip712.cached_domain_separator(),
+            false => {
+                (block::chainid() == T::INITIAL_CHAIN_ID).then_some(T::INITIAL_DOMAIN_SEPARATOR)
+            }
+        };
+
+        match cached {
+            Some(separator) => Ok(separator),
+            None => self.compute_domain_separator(),
+        }
+    }
+
+    /// Returns the fields of the EIP-712 domain, as described in ERC-5267.
+    #[selector(name = "eip712Domain")]
+    #[allow(clippy::type_complexity)]
+    pub fn eip712_domain(
+        &self,
+    ) -> Result<(FixedBytes<1>, String, String, U256, Address, B256, Vec<U256>)> {
+        Ok(self.domain()?.eip712_domain())
+    }
+
+    pub fn authorization_state(&self, authorizer: Address, nonce: B256) -> Result<bool> {
+        Ok(self.authorization_state.getter(authorizer).get(nonce))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn transfer_with_authorization<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        value: U256,
+        valid_after: U256,
+        valid_before: U256,
+        nonce: B256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> Result<()> {
+        Self::transfer_with_signed_authorization(
+            storage,
+            eip712::hash_struct(&TransferWithAuthorization {
+                from,
+                to,
+                value,
+                validAfter: valid_after,
+                validBefore: valid_before,
+                nonce: *nonce,
+            }),
+            from,
+            to,
+            value,
+            valid_after,
+            valid_before,
+            nonce,
+            v,
+            r,
+            s,
+        )
+    }
+
+    /// Like `transferWithAuthorization`, but only the payee may submit it, so the
+    /// authorization cannot be front-run outside of the payee's own transaction.
+    #[allow(clippy::too_many_arguments)]
+    pub fn receive_with_authorization<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        value: U256,
+        valid_after: U256,
+        valid_before: U256,
+        nonce: B256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> Result<()> {
+        if to != msg::sender() {
+            return Err(ERC20Error::CallerNotPayee(CallerNotPayee {}));
+        }
+
+        Self::transfer_with_signed_authorization(
+            storage,
+            eip712::hash_struct(&ReceiveWithAuthorization {
+                from,
+                to,
+                value,
+                validAfter: valid_after,
+                validBefore: valid_before,
+                nonce: *nonce,
+            }),
+            from,
+            to,
+            value,
+            valid_after,
+            valid_before,
+            nonce,
+            v,
+            r,
+            s,
+        )
+    }
+
+    pub fn cancel_authorization(
+        &mut self,
+        authorizer: Address,
+        nonce: B256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> Result<()> {
+        if self.authorization_state.getter(authorizer).get(nonce) {
+            return Err(ERC20Error::AuthorizationUsedOrCanceled(
+                AuthorizationUsedOrCanceled {},
+            ));
+        }
+
+        let struct_hash = eip712::hash_struct(&CancelAuthorization {
+            authorizer,
+            nonce: *nonce,
+        });
+
+        let digest = self.hash_typed_data(struct_hash)?;
+
+        if Self::recover(digest, v, r, s)? != authorizer {
+            return Err(ERC20Error::InvalidSigner(InvalidSigner {}));
+        }
+
+        self.authorization_state
+            .setter(authorizer)
+            .insert(nonce, true);
+
+        evm::log(AuthorizationCanceled {
+            authorizer,
+            nonce: *nonce,
+        });
+
+        Ok(())
+    }
+
+    pub fn max_flash_loan(&self, token: Address) -> Result<U256> {
+        if !T::FLASH_MINT || token != contract::address() {
+            return Ok(U256::ZERO);
+        }
+
+        Ok(U256::MAX - self.total_supply.get())
+    }
+
+    pub fn flash_fee(&self, token: Address, amount: U256) -> Result<U256> {
+        if !T::FLASH_MINT || token != contract::address() {
+            return Err(ERC20Error::ERC3156UnsupportedToken(
+                ERC3156UnsupportedToken { token },
+            ));
+        }
+
+        match amount.checked_mul(U256::from(T::FLASH_FEE_BPS)) {
+            Some(fee) => Ok(fee / U256::from(10_000)),
+            None => Err(ERC20Error::ERC3156FeeOverflow(ERC3156FeeOverflow { amount })),
+        }
+    }
+
+    pub fn flash_loan<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        receiver: Address,
+        token: Address,
+        amount: U256,
+        data: Bytes,
+    ) -> Result<bool> {
+        let max_loan = storage.borrow_mut().max_flash_loan(token)?;
+
+        if amount > max_loan {
+            return Err(ERC20Error::ERC3156ExceededMaxLoan(ERC3156ExceededMaxLoan {
+                maxLoan: max_loan,
+            }));
+        }
+
+        let fee = storage.borrow_mut().flash_fee(token, amount)?;
+
+        Self::mint(storage, receiver, amount)?;
+
+        let borrower = IERC3156FlashBorrower::new(receiver);
+        let result = borrower.on_flash_loan(&mut *storage, msg::sender(), token, amount, fee, data.0)?;
+
+        // 0x439148f0bbc682ca079e46d6e2c2f0c1e3b820f1a291b069d8882abf8cf18dd9 = keccak256("ERC3156FlashBorrower.onFlashLoan")
+        if result != crypto::keccak(b"ERC3156FlashBorrower.onFlashLoan") {
+            return Err(ERC20Error::ERC3156InvalidReceiver(ERC3156InvalidReceiver {
+                receiver,
+            }));
+        }
+
+        // The borrower repays by approving the token to take the amount and fee.
+        storage
+            .borrow_mut()
+            .spend_allowance(receiver, contract::address(), amount + fee)?;
+
+        if fee == U256::ZERO || T::FLASH_FEE_RECEIVER.is_zero() {
+            Self::burn(storage, receiver, amount + fee)?;
         } else {
-            Ok(ERC20::<T>::compute_domain_separator()?)
+            Self::burn(storage, receiver, amount)?;
+            Self::transfer_tokens(storage, receiver, T::FLASH_FEE_RECEIVER, fee)?;
+        }
+
+        Ok(true)
+    }
+}
+
+sol_interface! {
+    interface IERC3156FlashBorrower {
+        function onFlashLoan(address initiator, address token, uint256 amount, uint256 fee, bytes calldata data) external returns (bytes32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        sign,
+        signer,
+        Host,
+        Log,
+        ResultExt,
+        ALICE,
+        BOB,
+        CAROL,
+    };
+    use alloy_primitives::{
+        address,
+        b256,
+        keccak256,
+    };
+    use alloy_sol_types::{
+        SolEvent,
+        SolType,
+    };
+
+    struct TestParams;
+
+    sol! {
+        event Checkpoint(address indexed account, uint256 balance);
+
+        error Blocked(address account);
+        error NotFactory(address caller);
+    }
+
+    impl ERC20Params for TestParams {
+        const NAME: &'static str = "Test Token";
+        const SYMBOL: &'static str = "TEST";
+        const DECIMALS: u8 = 18;
+        const INITIAL_CHAIN_ID: u64 = 1;
+        const INITIAL_DOMAIN_SEPARATOR: B256 = B256::repeat_byte(0xde);
+        const FLASH_MINT: bool = true;
+        const FLASH_FEE_BPS: u64 = 5;
+        const FLASH_FEE_RECEIVER: Address = FEE_RECEIVER;
+
+        fn before_token_transfer<S: TopLevelStorage + BorrowMut<ERC20<Self>>>(
+            _storage: &mut S,
+            from: Address,
+            to: Address,
+            _amount: U256,
+        ) -> Result<(), Vec<u8>> {
+            match [from, to].contains(&BLOCKED) {
+                true => Err(Blocked { account: BLOCKED }.encode()),
+                false => Ok(()),
+            }
+        }
+
+        fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC20<Self>>>(
+            storage: &mut S,
+            from: Address,
+            to: Address,
+            _amount: U256,
+        ) -> Result<(), Vec<u8>> {
+            for account in [from, to] {
+                if !account.is_zero() {
+                    let balance = storage.borrow_mut().balance.get(account);
+                    evm::log(Checkpoint { account, balance });
+                }
+            }
+            Ok(())
+        }
+    }
+
+    sol_storage! {
+        pub struct TestToken {
+            #[borrow]
+            ERC20<TestParams> erc20;
+        }
+    }
+
+    unsafe impl TopLevelStorage for TestToken {}
+
+    type Token = ERC20<TestParams>;
+
+    const BLOCKED: Address = address!("000000000000000000000000000000000000dead");
+    const FEE_RECEIVER: Address = address!("0000000000000000000000000000000000000fee");
+    const TOKEN: Address = address!("000000000000000000000000000000000000c0de");
+
+    fn setup() -> (Host, TestToken) {
+        let host = Host::new();
+        host.set_chain_id(1);
+        host.set_contract_address(TOKEN);
+        let token = host.contract::<TestToken>();
+        (host, token)
+    }
+
+    #[test]
+    fn metadata() {
+        let (_host, token) = setup();
+
+        assert_eq!(token.erc20.name().unwrap_ok(), "Test Token");
+        assert_eq!(token.erc20.symbol().unwrap_ok(), "TEST");
+        assert_eq!(token.erc20.decimals().unwrap_ok(), 18);
+    }
+
+    #[test]
+    fn initialize_constant_metadata() {
+        let (_host, mut token) = setup();
+
+        let revert =
+            Token::initialize(&mut token, "Other".into(), "OTHER".into(), 6).unwrap_revert();
+
+        assert_eq!(revert, MetadataIsConstant {}.encode());
+        assert_eq!(token.erc20.name().unwrap_ok(), "Test Token");
+    }
+
+    #[test]
+    fn mint() {
+        let (host, mut token) = setup();
+
+        Token::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+
+        assert_eq!(token.erc20.total_supply().unwrap_ok(), U256::from(1_000));
+        assert_eq!(token.erc20.balance_of(ALICE).unwrap_ok(), U256::from(1_000));
+        assert!(host.emitted(&Transfer {
+            from: Address::ZERO,
+            to: ALICE,
+            amount: U256::from(1_000),
+        }));
+    }
+
+    #[test]
+    fn mint_persists_total_supply_in_first_slot() {
+        let (host, mut token) = setup();
+
+        Token::mint(&mut token, ALICE, U256::from(1337)).unwrap_ok();
+
+        assert_eq!(host.storage(U256::ZERO), B256::from(U256::from(1337)));
+    }
+
+    #[test]
+    fn burn() {
+        let (host, mut token) = setup();
+
+        Token::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+        Token::burn(&mut token, ALICE, U256::from(900)).unwrap_ok();
+
+        assert_eq!(token.erc20.total_supply().unwrap_ok(), U256::from(100));
+        assert_eq!(token.erc20.balance_of(ALICE).unwrap_ok(), U256::from(100));
+        assert!(host.emitted(&Transfer {
+            from: ALICE,
+            to: Address::ZERO,
+            amount: U256::from(900),
+        }));
+    }
+
+    #[test]
+    fn burn_insufficient_balance() {
+        let (_host, mut token) = setup();
+
+        Token::mint(&mut token, ALICE, U256::from(900)).unwrap_ok();
+
+        let revert = Token::burn(&mut token, ALICE, U256::from(1_000)).unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ERC20InsufficientBalance {
+                sender: ALICE,
+                balance: U256::from(900),
+                needed: U256::from(1_000),
+            }
+            .encode()
+        );
+        assert_eq!(token.erc20.total_supply().unwrap_ok(), U256::from(900));
+    }
+
+    #[test]
+    fn approve() {
+        let (host, mut token) = setup();
+
+        host.set_sender(ALICE);
+        assert!(token.erc20.approve(BOB, U256::from(1_000)).unwrap_ok());
+
+        assert_eq!(token.erc20.allowance(ALICE, BOB).unwrap_ok(), U256::from(1_000));
+        assert!(host.emitted(&Approval {
+            owner: ALICE,
+            spender: BOB,
+            amount: U256::from(1_000),
+        }));
+    }
+
+    #[test]
+    fn transfer() {
+        let (host, mut token) = setup();
+
+        Token::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+
+        host.set_sender(ALICE);
+        assert!(Token::transfer(&mut token, BOB, U256::from(1_000)).unwrap_ok());
+
+        assert_eq!(token.erc20.total_supply().unwrap_ok(), U256::from(1_000));
+        assert_eq!(token.erc20.balance_of(ALICE).unwrap_ok(), U256::ZERO);
+        assert_eq!(token.erc20.balance_of(BOB).unwrap_ok(), U256::from(1_000));
+        assert!(host.emitted(&Transfer {
+            from: ALICE,
+            to: BOB,
+            amount: U256::from(1_000),
+        }));
+    }
+
+    #[test]
+    fn transfer_insufficient_balance() {
+        let (host, mut token) = setup();
+
+        Token::mint(&mut token, ALICE, U256::from(900)).unwrap_ok();
+
+        host.set_sender(ALICE);
+        let revert = Token::transfer(&mut token, BOB, U256::from(1_000)).unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ERC20InsufficientBalance {
+                sender: ALICE,
+                balance: U256::from(900),
+                needed: U256::from(1_000),
+            }
+            .encode()
+        );
+        assert_eq!(token.erc20.balance_of(ALICE).unwrap_ok(), U256::from(900));
+        assert_eq!(token.erc20.balance_of(BOB).unwrap_ok(), U256::ZERO);
+    }
+
+    #[test]
+    fn transfer_from() {
+        let (host, mut token) = setup();
+
+        Token::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+
+        host.set_sender(ALICE);
+        token.erc20.approve(BOB, U256::from(1_000)).unwrap_ok();
+
+        host.set_sender(BOB);
+        assert!(Token::transfer_from(&mut token, ALICE, CAROL, U256::from(1_000)).unwrap_ok());
+
+        assert_eq!(token.erc20.allowance(ALICE, BOB).unwrap_ok(), U256::ZERO);
+        assert_eq!(token.erc20.balance_of(ALICE).unwrap_ok(), U256::ZERO);
+        assert_eq!(token.erc20.balance_of(CAROL).unwrap_ok(), U256::from(1_000));
+        assert!(host.emitted(&Transfer {
+            from: ALICE,
+            to: CAROL,
+            amount: U256::from(1_000),
+        }));
+    }
+
+    #[test]
+    fn transfer_from_infinite_approve() {
+        let (host, mut token) = setup();
+
+        Token::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+
+        host.set_sender(ALICE);
+        token.erc20.approve(BOB, U256::MAX).unwrap_ok();
+
+        host.set_sender(BOB);
+        Token::transfer_from(&mut token, ALICE, CAROL, U256::from(1_000)).unwrap_ok();
+
+        assert_eq!(token.erc20.allowance(ALICE, BOB).unwrap_ok(), U256::MAX);
+        assert_eq!(token.erc20.balance_of(CAROL).unwrap_ok(), U256::from(1_000));
+    }
+
+    #[test]
+    fn transfer_from_insufficient_allowance() {
+        let (host, mut token) = setup();
+
+        Token::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+
+        host.set_sender(ALICE);
+        token.erc20.approve(BOB, U256::from(900)).unwrap_ok();
+
+        host.set_sender(BOB);
+        let revert = Token::transfer_from(&mut token, ALICE, CAROL, U256::from(1_000))
+            .unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ERC20InsufficientAllowance {
+                spender: BOB,
+                allowance: U256::from(900),
+                needed: U256::from(1_000),
+            }
+            .encode()
+        );
+    }
+
+    #[test]
+    fn transfer_from_insufficient_balance() {
+        let (host, mut token) = setup();
+
+        Token::mint(&mut token, ALICE, U256::from(900)).unwrap_ok();
+
+        host.set_sender(ALICE);
+        token.erc20.approve(BOB, U256::from(1_000)).unwrap_ok();
+
+        host.set_sender(BOB);
+        let revert = Token::transfer_from(&mut token, ALICE, CAROL, U256::from(1_000))
+            .unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ERC20InsufficientBalance {
+                sender: ALICE,
+                balance: U256::from(900),
+                needed: U256::from(1_000),
+            }
+            .encode()
+        );
+    }
+
+    #[test]
+    fn hooks_run_on_every_balance_change() {
+        let (host, mut token) = setup();
+
+        Token::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+        host.set_sender(ALICE);
+        Token::transfer(&mut token, BOB, U256::from(300)).unwrap_ok();
+        token.erc20.approve(CAROL, U256::from(200)).unwrap_ok();
+        host.set_sender(CAROL);
+        Token::transfer_from(&mut token, ALICE, CAROL, U256::from(200)).unwrap_ok();
+        Token::burn(&mut token, BOB, U256::from(100)).unwrap_ok();
+
+        let checkpoints = [
+            (ALICE, 1_000),
+            (ALICE, 700),
+            (BOB, 300),
+            (ALICE, 500),
+            (CAROL, 200),
+            (BOB, 200),
+        ];
+        let logs: Vec<_> = checkpoints
+            .into_iter()
+            .map(|(account, balance)| {
+                Log::from_event(&Checkpoint {
+                    account,
+                    balance: U256::from(balance),
+                })
+            })
+            .collect();
+        let emitted: Vec<_> = host
+            .logs()
+            .into_iter()
+            .filter(|log| log.topics[0] == Checkpoint::SIGNATURE_HASH)
+            .collect();
+        assert_eq!(emitted, logs);
+    }
+
+    #[test]
+    fn hook_reverts_mint() {
+        let (_host, mut token) = setup();
+
+        let revert = Token::mint(&mut token, BLOCKED, U256::from(1_000)).unwrap_revert();
+
+        assert_eq!(revert, Blocked { account: BLOCKED }.encode());
+        assert_eq!(token.erc20.total_supply().unwrap_ok(), U256::ZERO);
+    }
+
+    #[test]
+    fn hook_reverts_transfer() {
+        let (host, mut token) = setup();
+
+        Token::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+
+        host.set_sender(ALICE);
+        let revert = Token::transfer(&mut token, BLOCKED, U256::from(1_000)).unwrap_revert();
+
+        assert_eq!(revert, Blocked { account: BLOCKED }.encode());
+        assert_eq!(token.erc20.balance_of(ALICE).unwrap_ok(), U256::from(1_000));
+    }
+
+    #[test]
+    fn permit_past_deadline() {
+        let (host, mut token) = setup();
+
+        host.set_timestamp(1_000);
+
+        let revert = Token::permit(
+            &mut token,
+            ALICE,
+            BOB,
+            U256::from(1_000),
+            U256::from(999),
+            27,
+            B256::ZERO,
+            B256::ZERO,
+        )
+        .unwrap_revert();
+
+        assert_eq!(revert, PermitDeadlineExpired {}.encode());
+    }
+
+    const OWNER_KEY: B256 = B256::repeat_byte(0x42);
+
+    // keccak256("Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)")
+    const PERMIT_TYPEHASH: B256 =
+        b256!("6e71edae12b1b97f4d1f60370fef10105fa2faae0126114a169c64845d6126c9");
+
+    fn permit_digest(
+        token: &ERC20<TestParams>,
+        owner: Address,
+        spender: Address,
+        value: U256,
+        nonce: U256,
+        deadline: U256,
+    ) -> B256 {
+        let struct_hash = keccak256(
+            <sol! { (bytes32, address, address, uint256, uint256, uint256) }>::encode(&(
+                *PERMIT_TYPEHASH,
+                owner,
+                spender,
+                value,
+                nonce,
+                deadline,
+            )),
+        );
+        let domain_separator = token.domain_separator().unwrap_ok();
+
+        keccak256([&[0x19, 0x01], &domain_separator[..], &struct_hash[..]].concat())
+    }
+
+    #[test]
+    fn permit() {
+        let (host, mut token) = setup();
+        let owner = signer(OWNER_KEY);
+
+        let digest = permit_digest(&token.erc20, owner, BOB, U256::from(1_000), U256::ZERO, U256::MAX);
+        let (v, r, s) = sign(OWNER_KEY, digest);
+
+        Token::permit(&mut token, owner, BOB, U256::from(1_000), U256::MAX, v, r, s).unwrap_ok();
+
+        assert_eq!(token.erc20.allowance(owner, BOB).unwrap_ok(), U256::from(1_000));
+        assert_eq!(token.erc20.nonces(owner).unwrap_ok(), U256::from(1));
+        assert_eq!(token.erc20.balance_of(owner).unwrap_ok(), U256::ZERO);
+        assert!(host.emitted(&Approval {
+            owner,
+            spender: BOB,
+            amount: U256::from(1_000),
+        }));
+    }
+
+    // A permit of 1_000 to `BOB` with nonce 0 and no deadline, on chain 5 where the domain
+    // separator is computed rather than cached. The digest and signature were produced with
+    // OpenSSL's KECCAK-256 and secp256k1 instead of this crate, from a throwaway key.
+    const EXTERNAL_OWNER: Address = address!("1e01115bfaf7fbf339b6d305aff91e95deaa9ac7");
+    const EXTERNAL_DOMAIN_SEPARATOR: B256 =
+        b256!("9cc391fd57076e7701f7e62582913cda64b99e71960c5384718b5ff629796929");
+    const EXTERNAL_DIGEST: B256 =
+        b256!("7f69b19b36c4bfef35f1d922c5db76ed6ef9f3dddc77b40ff1c8352e6e67c541");
+    const EXTERNAL_R: B256 =
+        b256!("5bd54fdc9020b712c840ad72a40cda8dd8d7c06bcc5c3984396431d1c319b8e1");
+    const EXTERNAL_S: B256 =
+        b256!("19c7ed713dec700c37f234196780497ae4971b723551bd4c15869f6ac5762285");
+
+    #[test]
+    fn permit_external_vector() {
+        let (host, mut token) = setup();
+        host.set_chain_id(5);
+
+        assert_eq!(token.erc20.domain_separator().unwrap_ok(), EXTERNAL_DOMAIN_SEPARATOR);
+        assert_eq!(
+            permit_digest(
+                &token.erc20,
+                EXTERNAL_OWNER,
+                BOB,
+                U256::from(1_000),
+                U256::ZERO,
+                U256::MAX,
+            ),
+            EXTERNAL_DIGEST
+        );
+
+        Token::permit(
+            &mut token,
+            EXTERNAL_OWNER,
+            BOB,
+            U256::from(1_000),
+            U256::MAX,
+            28,
+            EXTERNAL_R,
+            EXTERNAL_S,
+        )
+        .unwrap_ok();
+
+        assert_eq!(token.erc20.allowance(EXTERNAL_OWNER, BOB).unwrap_ok(), U256::from(1_000));
+        assert_eq!(token.erc20.nonces(EXTERNAL_OWNER).unwrap_ok(), U256::from(1));
+    }
+
+    const WALLET: Address = address!("00000000000000000000000000000000000a11e7");
+
+    #[test]
+    fn permit_smart_wallet() {
+        let (host, mut token) = setup();
+
+        // An ERC-1271 wallet answering `isValidSignature` with its magic value.
+        let mut magic_value = [0u8; 32];
+        magic_value[..4].copy_from_slice(&0x1626ba7eu32.to_be_bytes());
+        host.mock_call(WALLET, Ok(magic_value.to_vec()));
+
+        Token::permit(
+            &mut token,
+            WALLET,
+            BOB,
+            U256::from(1_000),
+            U256::MAX,
+            27,
+            B256::ZERO,
+            B256::ZERO,
+        )
+        .unwrap_ok();
+
+        assert_eq!(token.erc20.allowance(WALLET, BOB).unwrap_ok(), U256::from(1_000));
+        assert_eq!(token.erc20.nonces(WALLET).unwrap_ok(), U256::from(1));
+    }
+
+    #[test]
+    fn permit_smart_wallet_rejects() {
+        let (host, mut token) = setup();
+        host.mock_call(WALLET, Ok([0u8; 32].to_vec()));
+
+        let revert = Token::permit(
+            &mut token,
+            WALLET,
+            BOB,
+            U256::from(1_000),
+            U256::MAX,
+            27,
+            B256::ZERO,
+            B256::ZERO,
+        )
+        .unwrap_revert();
+
+        assert_eq!(revert, InvalidSigner {}.encode());
+
+        // The wallet's rejection wins over what ECDSA would make of the signature.
+        let revert = Token::permit(
+            &mut token,
+            WALLET,
+            BOB,
+            U256::from(1_000),
+            U256::MAX,
+            27,
+            B256::ZERO,
+            B256::repeat_byte(0xff),
+        )
+        .unwrap_revert();
+
+        assert_eq!(revert, InvalidSigner {}.encode());
+    }
+
+    #[test]
+    fn permit_replay() {
+        let (_host, mut token) = setup();
+        let owner = signer(OWNER_KEY);
+
+        let digest = permit_digest(&token.erc20, owner, BOB, U256::from(1_000), U256::ZERO, U256::MAX);
+        let (v, r, s) = sign(OWNER_KEY, digest);
+
+        Token::permit(&mut token, owner, BOB, U256::from(1_000), U256::MAX, v, r, s).unwrap_ok();
+        let revert = Token::permit(&mut token, owner, BOB, U256::from(1_000), U256::MAX, v, r, s)
+            .unwrap_revert();
+
+        assert_eq!(revert, InvalidSigner {}.encode());
+    }
+
+    #[test]
+    fn permit_wrong_owner() {
+        let (_host, mut token) = setup();
+        let owner = signer(OWNER_KEY);
+
+        let digest = permit_digest(&token.erc20, ALICE, BOB, U256::from(1_000), U256::ZERO, U256::MAX);
+        let (v, r, s) = sign(OWNER_KEY, digest);
+
+        let revert = Token::permit(&mut token, ALICE, BOB, U256::from(1_000), U256::MAX, v, r, s)
+            .unwrap_revert();
+
+        assert_eq!(revert, InvalidSigner {}.encode());
+        assert_eq!(token.erc20.allowance(owner, BOB).unwrap_ok(), U256::ZERO);
+    }
+
+    #[test]
+    fn permit_unrecoverable_signature() {
+        let (_host, mut token) = setup();
+
+        let revert = Token::permit(
+            &mut token,
+            ALICE,
+            BOB,
+            U256::from(1_000),
+            U256::MAX,
+            27,
+            B256::ZERO,
+            B256::ZERO,
+        )
+        .unwrap_revert();
+
+        assert_eq!(revert, InvalidSigner {}.encode());
+    }
+
+    #[test]
+    fn permit_high_s() {
+        let (_host, mut token) = setup();
+        let owner = signer(OWNER_KEY);
+
+        let digest = permit_digest(&token.erc20, owner, BOB, U256::from(1_000), U256::ZERO, U256::MAX);
+        let (v, r, s) = sign(OWNER_KEY, digest);
+
+        // The same signature, mirrored into the upper half of the curve order.
+        let n = U256::from_be_bytes(
+            b256!("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141").0,
+        );
+        let high_s = B256::from(n - U256::from_be_bytes(s.0));
+        let high_v = if v == 27 { 28 } else { 27 };
+
+        let revert = Token::permit(
+            &mut token,
+            owner,
+            BOB,
+            U256::from(1_000),
+            U256::MAX,
+            high_v,
+            r,
+            high_s,
+        )
+        .unwrap_revert();
+
+        assert_eq!(revert, InvalidSignatureS { s: *high_s }.encode());
+    }
+
+    // keccak256("TransferWithAuthorization(address from,address to,uint256 value,uint256 validAfter,uint256 validBefore,bytes32 nonce)")
+    const TRANSFER_WITH_AUTHORIZATION_TYPEHASH: B256 =
+        b256!("7c7c6cdb67a18743f49ec6fa9b35f50d52ed05cbed4cc592e13b44501c1a2267");
+
+    // keccak256("ReceiveWithAuthorization(address from,address to,uint256 value,uint256 validAfter,uint256 validBefore,bytes32 nonce)")
+    const RECEIVE_WITH_AUTHORIZATION_TYPEHASH: B256 =
+        b256!("d099cc98ef71107a616c4f0f941f04c322d8e254fe26b3c6668db87aae413de8");
+
+    // keccak256("CancelAuthorization(address authorizer,bytes32 nonce)")
+    const CANCEL_AUTHORIZATION_TYPEHASH: B256 =
+        b256!("158b0a9edf7a828aad02f63cd515c68ef2f50ba807396f6d12842833a1597429");
+
+    const AUTHORIZATION_NONCE: B256 = B256::repeat_byte(0x99);
+
+    fn typed_data_digest(struct_hash: B256) -> B256 {
+        keccak256(
+            [
+                &[0x19, 0x01],
+                &TestParams::INITIAL_DOMAIN_SEPARATOR[..],
+                &struct_hash[..],
+            ]
+            .concat(),
+        )
+    }
+
+    fn sign_authorization(typehash: B256, from: Address, to: Address) -> (u8, B256, B256) {
+        let struct_hash = keccak256(
+            <sol! { (bytes32, address, address, uint256, uint256, uint256, bytes32) }>::encode(&(
+                *typehash,
+                from,
+                to,
+                U256::from(1_000),
+                U256::ZERO,
+                U256::MAX,
+                *AUTHORIZATION_NONCE,
+            )),
+        );
+
+        sign(OWNER_KEY, typed_data_digest(struct_hash))
+    }
+
+    #[test]
+    fn transfer_with_authorization() {
+        let (host, mut token) = setup();
+        let owner = signer(OWNER_KEY);
+
+        Token::mint(&mut token, owner, U256::from(1_000)).unwrap_ok();
+        host.set_timestamp(1_000);
+
+        let (v, r, s) = sign_authorization(TRANSFER_WITH_AUTHORIZATION_TYPEHASH, owner, BOB);
+        let transfer = |token: &mut TestToken| {
+            Token::transfer_with_authorization(
+                token,
+                owner,
+                BOB,
+                U256::from(1_000),
+                U256::ZERO,
+                U256::MAX,
+                AUTHORIZATION_NONCE,
+                v,
+                r,
+                s,
+            )
+        };
+
+        host.set_sender(CAROL);
+        transfer(&mut token).unwrap_ok();
+
+        assert_eq!(token.erc20.balance_of(BOB).unwrap_ok(), U256::from(1_000));
+        assert!(token
+            .erc20
+            .authorization_state(owner, AUTHORIZATION_NONCE)
+            .unwrap_ok());
+        assert!(host.emitted(&AuthorizationUsed {
+            authorizer: owner,
+            nonce: *AUTHORIZATION_NONCE,
+        }));
+
+        let revert = transfer(&mut token).unwrap_revert();
+        assert_eq!(revert, AuthorizationUsedOrCanceled {}.encode());
+    }
+
+    #[test]
+    fn transfer_with_authorization_outside_window() {
+        let (host, mut token) = setup();
+
+        let transfer = |token: &mut TestToken, valid_after: u64, valid_before: u64| {
+            Token::transfer_with_authorization(
+                token,
+                ALICE,
+                BOB,
+                U256::from(1_000),
+                U256::from(valid_after),
+                U256::from(valid_before),
+                AUTHORIZATION_NONCE,
+                27,
+                B256::ZERO,
+                B256::ZERO,
+            )
+            .unwrap_revert()
+        };
+
+        host.set_timestamp(1_000);
+
+        assert_eq!(transfer(&mut token, 1_000, 2_000), AuthorizationNotYetValid {}.encode());
+        assert_eq!(transfer(&mut token, 0, 1_000), AuthorizationExpired {}.encode());
+    }
+
+    #[test]
+    fn receive_with_authorization() {
+        let (host, mut token) = setup();
+        let owner = signer(OWNER_KEY);
+
+        Token::mint(&mut token, owner, U256::from(1_000)).unwrap_ok();
+        host.set_timestamp(1_000);
+
+        let (v, r, s) = sign_authorization(RECEIVE_WITH_AUTHORIZATION_TYPEHASH, owner, BOB);
+        let receive = |token: &mut TestToken| {
+            Token::receive_with_authorization(
+                token,
+                owner,
+                BOB,
+                U256::from(1_000),
+                U256::ZERO,
+                U256::MAX,
+                AUTHORIZATION_NONCE,
+                v,
+                r,
+                s,
+            )
+        };
+
+        host.set_sender(CAROL);
+        assert_eq!(receive(&mut token).unwrap_revert(), CallerNotPayee {}.encode());
+
+        host.set_sender(BOB);
+        receive(&mut token).unwrap_ok();
+
+        assert_eq!(token.erc20.balance_of(BOB).unwrap_ok(), U256::from(1_000));
+    }
+
+    #[test]
+    fn cancel_authorization() {
+        let (host, mut token) = setup();
+        let owner = signer(OWNER_KEY);
+
+        Token::mint(&mut token, owner, U256::from(1_000)).unwrap_ok();
+        host.set_timestamp(1_000);
+
+        let struct_hash = keccak256(<sol! { (bytes32, address, bytes32) }>::encode(&(
+            *CANCEL_AUTHORIZATION_TYPEHASH,
+            owner,
+            *AUTHORIZATION_NONCE,
+        )));
+        let (v, r, s) = sign(OWNER_KEY, typed_data_digest(struct_hash));
+        token
+            .erc20
+            .cancel_authorization(owner, AUTHORIZATION_NONCE, v, r, s)
+            .unwrap_ok();
+
+        assert!(host.emitted(&AuthorizationCanceled {
+            authorizer: owner,
+            nonce: *AUTHORIZATION_NONCE,
+        }));
+
+        let (v, r, s) = sign_authorization(TRANSFER_WITH_AUTHORIZATION_TYPEHASH, owner, BOB);
+        let revert = Token::transfer_with_authorization(
+            &mut token,
+            owner,
+            BOB,
+            U256::from(1_000),
+            U256::ZERO,
+            U256::MAX,
+            AUTHORIZATION_NONCE,
+            v,
+            r,
+            s,
+        )
+        .unwrap_revert();
+
+        assert_eq!(revert, AuthorizationUsedOrCanceled {}.encode());
+        assert_eq!(token.erc20.balance_of(owner).unwrap_ok(), U256::from(1_000));
+    }
+
+    #[test]
+    fn domain_separator_on_initial_chain() {
+        let (_host, token) = setup();
+
+        assert_eq!(
+            token.erc20.domain_separator().unwrap_ok(),
+            TestParams::INITIAL_DOMAIN_SEPARATOR
+        );
+    }
+
+    #[test]
+    fn domain_separator_on_other_chain() {
+        let (host, token) = setup();
+
+        host.set_chain_id(5);
+
+        assert_eq!(
+            token.erc20.domain_separator().unwrap_ok(),
+            token.erc20.compute_domain_separator().unwrap_ok()
+        );
+    }
+
+    #[test]
+    fn eip712_domain() {
+        let (_host, token) = setup();
+
+        assert_eq!(
+            token.erc20.eip712_domain().unwrap_ok(),
+            (
+                FixedBytes([0x0f]),
+                "Test Token".into(),
+                "1".into(),
+                U256::from(1),
+                TOKEN,
+                B256::ZERO,
+                Vec::new(),
+            )
+        );
+    }
+
+    const BORROWER: Address = address!("000000000000000000000000000000000000b044");
+
+    // keccak256("ERC3156FlashBorrower.onFlashLoan")
+    const ON_FLASH_LOAN: B256 =
+        b256!("439148f0bbc682ca079e46d6e2c2f0c1e3b820f1a291b069d8882abf8cf18dd9");
+
+    #[test]
+    fn flash_fee() {
+        let (_host, mut token) = setup();
+
+        Token::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+
+        assert_eq!(
+            token.erc20.flash_fee(TOKEN, U256::from(10_000)).unwrap_ok(),
+            U256::from(5)
+        );
+        assert_eq!(
+            token.erc20.max_flash_loan(TOKEN).unwrap_ok(),
+            U256::MAX - U256::from(1_000)
+        );
+        assert_eq!(token.erc20.max_flash_loan(ALICE).unwrap_ok(), U256::ZERO);
+
+        let revert = token.erc20.flash_fee(ALICE, U256::from(10_000)).unwrap_revert();
+        assert_eq!(revert, ERC3156UnsupportedToken { token: ALICE }.encode());
+
+        let revert = token.erc20.flash_fee(TOKEN, U256::MAX).unwrap_revert();
+        assert_eq!(revert, ERC3156FeeOverflow { amount: U256::MAX }.encode());
+    }
+
+    #[test]
+    fn flash_loan() {
+        let (host, mut token) = setup();
+
+        host.mock_call(BORROWER, Ok(ON_FLASH_LOAN.to_vec()));
+        Token::mint(&mut token, BORROWER, U256::from(5)).unwrap_ok();
+        host.set_sender(BORROWER);
+        token.erc20.approve(TOKEN, U256::from(10_005)).unwrap_ok();
+
+        host.set_sender(ALICE);
+        let borrowed =
+            Token::flash_loan(&mut token, BORROWER, TOKEN, U256::from(10_000), Bytes(vec![]));
+
+        assert!(borrowed.unwrap_ok());
+        assert_eq!(token.erc20.balance_of(BORROWER).unwrap_ok(), U256::ZERO);
+        assert_eq!(token.erc20.balance_of(FEE_RECEIVER).unwrap_ok(), U256::from(5));
+        assert_eq!(token.erc20.total_supply().unwrap_ok(), U256::from(5));
+        assert_eq!(token.erc20.allowance(BORROWER, TOKEN).unwrap_ok(), U256::ZERO);
+        assert!(host.emitted(&Transfer {
+            from: Address::ZERO,
+            to: BORROWER,
+            amount: U256::from(10_000),
+        }));
+        // The mint, burn and fee transfer all run the hooks.
+        assert!(host.emitted(&Checkpoint {
+            account: BORROWER,
+            balance: U256::from(10_005),
+        }));
+        assert!(host.emitted(&Checkpoint {
+            account: FEE_RECEIVER,
+            balance: U256::from(5),
+        }));
+    }
+
+    #[test]
+    fn flash_loan_without_repayment() {
+        let (host, mut token) = setup();
+
+        host.mock_call(BORROWER, Ok(ON_FLASH_LOAN.to_vec()));
+
+        let revert =
+            Token::flash_loan(&mut token, BORROWER, TOKEN, U256::from(10_000), Bytes(vec![]))
+                .unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ERC20InsufficientAllowance {
+                spender: TOKEN,
+                allowance: U256::ZERO,
+                needed: U256::from(10_005),
+            }
+            .encode()
+        );
+    }
+
+    #[test]
+    fn flash_loan_invalid_receiver() {
+        let (host, mut token) = setup();
+
+        host.mock_call(BORROWER, Ok(B256::ZERO.to_vec()));
+
+        let revert =
+            Token::flash_loan(&mut token, BORROWER, TOKEN, U256::from(10_000), Bytes(vec![]))
+                .unwrap_revert();
+
+        assert_eq!(revert, ERC3156InvalidReceiver { receiver: BORROWER }.encode());
+    }
+
+    #[test]
+    fn flash_loan_unsupported_token() {
+        let (_host, mut token) = setup();
+
+        let revert =
+            Token::flash_loan(&mut token, BORROWER, ALICE, U256::from(10_000), Bytes(vec![]))
+                .unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ERC3156ExceededMaxLoan {
+                maxLoan: U256::ZERO,
+            }
+            .encode()
+        );
+    }
+
+    struct RuntimeParams;
+
+    impl ERC20Params for RuntimeParams {
+        const NAME: &'static str = "";
+        const SYMBOL: &'static str = "";
+        const DECIMALS: u8 = 0;
+        const INITIAL_CHAIN_ID: u64 = 1;
+        const INITIAL_DOMAIN_SEPARATOR: B256 = B256::ZERO;
+        const RUNTIME_METADATA: bool = true;
+
+        fn before_initialize<S: TopLevelStorage + BorrowMut<ERC20<Self>>>(
+            _storage: &mut S,
+        ) -> Result<(), Vec<u8>> {
+            match msg::sender() {
+                FACTORY => Ok(()),
+                caller => Err(NotFactory { caller }.encode()),
+            }
+        }
+    }
+
+    sol_storage! {
+        pub struct RuntimeToken {
+            #[borrow]
+            ERC20<RuntimeParams> erc20;
         }
     }
+
+    unsafe impl TopLevelStorage for RuntimeToken {}
+
+    type Runtime = ERC20<RuntimeParams>;
+
+    const FACTORY: Address = address!("000000000000000000000000000000000000fac7");
+
+    fn runtime_setup() -> (Host, RuntimeToken) {
+        let host = Host::new();
+        host.set_sender(FACTORY);
+        let token = host.contract::<RuntimeToken>();
+        (host, token)
+    }
+
+    #[test]
+    fn initialize_runtime_metadata() {
+        let (_host, mut token) = runtime_setup();
+
+        Runtime::initialize(&mut token, "Ether Mail".into(), "MAIL".into(), 6).unwrap_ok();
+
+        assert_eq!(token.erc20.name().unwrap_ok(), "Ether Mail");
+        assert_eq!(token.erc20.symbol().unwrap_ok(), "MAIL");
+        assert_eq!(token.erc20.decimals().unwrap_ok(), 6);
+
+        let revert =
+            Runtime::initialize(&mut token, "Other".into(), "OTHER".into(), 18).unwrap_revert();
+
+        assert_eq!(revert, AlreadyInitialized {}.encode());
+        assert_eq!(token.erc20.name().unwrap_ok(), "Ether Mail");
+    }
+
+    #[test]
+    fn initialize_gated_by_hook() {
+        let (host, mut token) = runtime_setup();
+        host.set_sender(ALICE);
+
+        let revert =
+            Runtime::initialize(&mut token, "Other".into(), "OTHER".into(), 18).unwrap_revert();
+
+        assert_eq!(revert, NotFactory { caller: ALICE }.encode());
+        assert_eq!(token.erc20.name().unwrap_ok(), "");
+
+        host.set_sender(FACTORY);
+        Runtime::initialize(&mut token, "Ether Mail".into(), "MAIL".into(), 18).unwrap_ok();
+
+        assert_eq!(token.erc20.name().unwrap_ok(), "Ether Mail");
+    }
+
+    #[test]
+    fn domain_separator_from_runtime_name() {
+        let (host, mut token) = runtime_setup();
+
+        // The domain of the "Ether Mail" example in EIP-712.
+        host.set_chain_id(1);
+        host.set_contract_address(address!("cccccccccccccccccccccccccccccccccccccccc"));
+        Runtime::initialize(&mut token, "Ether Mail".into(), "MAIL".into(), 18).unwrap_ok();
+
+        assert!(host.emitted(&EIP712DomainChanged {}));
+        assert_eq!(
+            token.erc20.domain_separator().unwrap_ok(),
+            b256!("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+
+        host.set_chain_id(5);
+
+        assert_eq!(
+            token.erc20.domain_separator().unwrap_ok(),
+            token.erc20.compute_domain_separator().unwrap_ok()
+        );
+        assert_eq!(token.erc20.eip712_domain().unwrap_ok().3, U256::from(5));
+    }
+
+    struct MailParams;
+
+    impl ERC20Params for MailParams {
+        const NAME: &'static str = "Ether Mail";
+        const SYMBOL: &'static str = "MAIL";
+        const DECIMALS: u8 = 18;
+        const INITIAL_CHAIN_ID: u64 = 1;
+        const INITIAL_DOMAIN_SEPARATOR: B256 = B256::ZERO;
+    }
+
+    #[test]
+    fn compute_domain_separator() {
+        let host = Host::new();
+        let token = host.contract::<ERC20<MailParams>>();
+
+        // The domain of the "Ether Mail" example in EIP-712.
+        host.set_chain_id(1);
+        host.set_contract_address(address!("cccccccccccccccccccccccccccccccccccccccc"));
+
+        assert_eq!(
+            token.compute_domain_separator().unwrap_ok(),
+            b256!("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+    }
 }
@@ -0,0 +1,389 @@
+//! Provides a snapshot extension for the [`ERC20`] token.
+//!
+//! The [`ERC20Snapshot`] type records balances and the total supply at the time
+//! a snapshot is taken, for dividends or off-chain votes. Snapshots are only
+//! taken through [`ERC20Snapshot::snapshot`], so the inheriting contract decides
+//! who may take them and exposes it as it sees fit.
+//!
+//! Values are recorded lazily: an account's balance is only written the first
+//! time it changes after a snapshot, which the token's params report by
+//! forwarding [`ERC20Params::before_token_transfer`] to
+//! [`ERC20Snapshot::before_token_transfer`]. [`ERC20Snapshot`] is inherited
+//! alongside [`ERC20`]:
+//!
+//! ```ignore
+//! impl ERC20Params for MyParams {
+//!     // ...
+//!
+//!     fn before_token_transfer<S: TopLevelStorage + BorrowMut<ERC20<Self>>>(
+//!         storage: &mut S,
+//!         from: Address,
+//!         to: Address,
+//!         _amount: U256,
+//!     ) -> Result<(), Vec<u8>> {
+//!         ERC20Snapshot::<Self>::before_token_transfer(storage, from, to);
+//!         Ok(())
+//!     }
+//! }
+//!
+//! sol_storage! {
+//!     #[entrypoint]
+//!     pub struct MyToken {
+//!         #[borrow]
+//!         ERC20<MyParams> erc20;
+//!         #[borrow]
+//!         ERC20Snapshot<MyParams> snapshots;
+//!     }
+//! }
+//!
+//! #[external]
+//! #[inherit(ERC20Snapshot<MyParams>, ERC20<MyParams>)]
+//! impl MyToken {}
+//! ```
+//!
+//! The snapshots live in the `rustmate.ERC20Snapshot` namespace, see
+//! [`crate::utils::namespace`].
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use crate::tokens::erc20::{
+    ERC20Error,
+    ERC20Params,
+    ERC20,
+};
+use crate::utils::namespace;
+use alloc::vec::Vec;
+use alloy_primitives::{
+    b256,
+    Address,
+    B256,
+    U256,
+};
+use alloy_sol_types::{
+    sol,
+    SolError,
+};
+use core::{
+    borrow::{
+        Borrow,
+        BorrowMut,
+    },
+    marker::PhantomData,
+};
+use stylus_sdk::{
+    evm,
+    prelude::*,
+};
+
+sol_storage! {
+    /// ERC20Snapshot records [`ERC20`] balances at each snapshot
+    pub struct ERC20Snapshot<T: ERC20Params> {
+        PhantomData<T> phantom;
+    }
+
+    /// The snapshots, kept in the `rustmate.ERC20Snapshot` namespace
+    pub struct SnapshotStorage {
+        mapping(address => Snapshots) account_balance_snapshots;
+        Snapshots total_supply_snapshots;
+        uint256 current_snapshot_id;
+    }
+
+    /// The values held before each snapshot in `ids` was superseded
+    pub struct Snapshots {
+        uint256[] ids;
+        uint256[] values;
+    }
+}
+
+/// keccak256(abi.encode(uint256(keccak256("rustmate.ERC20Snapshot")) - 1)) & ~bytes32(uint256(0xff))
+const STORAGE_SLOT: B256 =
+    b256!("6b342a9f2d624fefa661ee0cf7ca14226b8efd0cbecd639270bea8789c5c1200");
+
+// Declare events and Solidity error types
+sol! {
+    event Snapshot(uint256 id);
+
+    error SnapshotIdZero();
+    error NonexistentSnapshot(uint256 id);
+}
+
+/// Represents the ways methods may fail.
+pub enum ERC20SnapshotError {
+    SnapshotIdZero(SnapshotIdZero),
+    NonexistentSnapshot(NonexistentSnapshot),
+    ERC20(ERC20Error),
+}
+
+impl From<ERC20Error> for ERC20SnapshotError {
+    fn from(err: ERC20Error) -> Self {
+        Self::ERC20(err)
+    }
+}
+
+/// We will soon provide a `#[derive(SolidityError)]` to clean this up.
+impl From<ERC20SnapshotError> for Vec<u8> {
+    fn from(val: ERC20SnapshotError) -> Self {
+        match val {
+            ERC20SnapshotError::SnapshotIdZero(err) => err.encode(),
+            ERC20SnapshotError::NonexistentSnapshot(err) => err.encode(),
+            ERC20SnapshotError::ERC20(err) => err.into(),
+        }
+    }
+}
+
+/// Simplifies the result type for the contract's methods.
+type Result<T, E = ERC20SnapshotError> = core::result::Result<T, E>;
+
+impl Snapshots {
+    /// Returns the value recorded for snapshot `id`, if it changed since.
+    fn value_at(&self, id: U256) -> Option<U256> {
+        // Find the first snapshot at or after `id`; it holds the value at `id`.
+        let (mut low, mut high) = (0, self.ids.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.ids.get(mid).unwrap() < id {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        self.values.get(low)
+    }
+
+    /// Records `value` for snapshot `id`, unless a value was already recorded.
+    fn update(&mut self, id: U256, value: U256) {
+        let last = match self.ids.len() {
+            0 => U256::ZERO,
+            len => self.ids.get(len - 1).unwrap(),
+        };
+
+        if last < id {
+            self.ids.push(id);
+            self.values.push(value);
+        }
+    }
+}
+
+impl SnapshotStorage {
+    /// Returns the snapshots of the token in its namespace.
+    fn load() -> Self {
+        namespace::load(STORAGE_SLOT)
+    }
+
+    fn check_snapshot(&self, id: U256) -> Result<()> {
+        if id == U256::ZERO {
+            return Err(ERC20SnapshotError::SnapshotIdZero(SnapshotIdZero {}));
+        }
+
+        if id > self.current_snapshot_id.get() {
+            return Err(ERC20SnapshotError::NonexistentSnapshot(
+                NonexistentSnapshot { id },
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: ERC20Params> ERC20Snapshot<T> {
+    /// Takes a snapshot of the balances and total supply, returning its id.
+    pub fn snapshot(&mut self) -> U256 {
+        let mut snapshots = SnapshotStorage::load();
+        let id = snapshots.current_snapshot_id.get() + U256::from(1);
+        snapshots.current_snapshot_id.set(id);
+
+        evm::log(Snapshot { id });
+
+        id
+    }
+
+    /// Records the values about to change when tokens move from `from` to `to`.
+    /// Call it from [`ERC20Params::before_token_transfer`], so that it sees mints,
+    /// burns and every kind of transfer.
+    pub fn before_token_transfer<S>(storage: &mut S, from: Address, to: Address)
+    where
+        S: TopLevelStorage + BorrowMut<ERC20<T>>,
+    {
+        let token: &mut ERC20<T> = storage.borrow_mut();
+        let total_supply = token.total_supply.get();
+        let from_balance = token.balance.get(from);
+        let to_balance = token.balance.get(to);
+
+        let mut snapshots = SnapshotStorage::load();
+        let id = snapshots.current_snapshot_id.get();
+
+        if from.is_zero() || to.is_zero() {
+            snapshots.total_supply_snapshots.update(id, total_supply);
+        }
+        if !from.is_zero() {
+            snapshots
+                .account_balance_snapshots
+                .setter(from)
+                .update(id, from_balance);
+        }
+        if !to.is_zero() {
+            snapshots
+                .account_balance_snapshots
+                .setter(to)
+                .update(id, to_balance);
+        }
+    }
+}
+
+#[external]
+#[inherit(ERC20<T>)]
+impl<T: ERC20Params> ERC20Snapshot<T> {
+    pub fn balance_of_at<S>(storage: &S, account: Address, id: U256) -> Result<U256>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC20<T>>,
+    {
+        let snapshots = SnapshotStorage::load();
+        snapshots.check_snapshot(id)?;
+
+        match snapshots.account_balance_snapshots.getter(account).value_at(id) {
+            Some(balance) => Ok(balance),
+            None => Ok(Borrow::<ERC20<T>>::borrow(storage).balance.get(account)),
+        }
+    }
+
+    pub fn total_supply_at<S>(storage: &S, id: U256) -> Result<U256>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC20<T>>,
+    {
+        let snapshots = SnapshotStorage::load();
+        snapshots.check_snapshot(id)?;
+
+        match snapshots.total_supply_snapshots.value_at(id) {
+            Some(total_supply) => Ok(total_supply),
+            None => Ok(Borrow::<ERC20<T>>::borrow(storage).total_supply.get()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        Host,
+        ResultExt,
+        ALICE,
+        BOB,
+        CAROL,
+    };
+
+    struct TestParams;
+
+    impl ERC20Params for TestParams {
+        const NAME: &'static str = "Snapshot Token";
+        const SYMBOL: &'static str = "SNAP";
+        const DECIMALS: u8 = 18;
+        const INITIAL_CHAIN_ID: u64 = 1;
+        const INITIAL_DOMAIN_SEPARATOR: B256 = B256::ZERO;
+
+        fn before_token_transfer<S: TopLevelStorage + BorrowMut<ERC20<Self>>>(
+            storage: &mut S,
+            from: Address,
+            to: Address,
+            _amount: U256,
+        ) -> Result<(), Vec<u8>> {
+            ERC20Snapshot::<Self>::before_token_transfer(storage, from, to);
+            Ok(())
+        }
+    }
+
+    sol_storage! {
+        pub struct TestToken {
+            #[borrow]
+            ERC20<TestParams> erc20;
+            #[borrow]
+            ERC20Snapshot<TestParams> snapshots;
+        }
+    }
+
+    unsafe impl TopLevelStorage for TestToken {}
+
+    type Snapshots = ERC20Snapshot<TestParams>;
+
+    #[test]
+    fn snapshot() {
+        let host = Host::new();
+        let mut token = host.contract::<TestToken>();
+
+        assert_eq!(token.snapshots.snapshot(), U256::from(1));
+        assert_eq!(token.snapshots.snapshot(), U256::from(2));
+        assert!(host.emitted(&Snapshot { id: U256::from(2) }));
+    }
+
+    #[test]
+    fn balances_at_snapshots() {
+        let host = Host::new();
+        let mut token = host.contract::<TestToken>();
+
+        ERC20::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+        let first = token.snapshots.snapshot();
+
+        host.set_sender(ALICE);
+        ERC20::transfer(&mut token, BOB, U256::from(300)).unwrap_ok();
+        let second = token.snapshots.snapshot();
+
+        token.erc20.approve(BOB, U256::from(200)).unwrap_ok();
+        host.set_sender(BOB);
+        ERC20::transfer_from(&mut token, ALICE, CAROL, U256::from(200)).unwrap_ok();
+        ERC20::burn(&mut token, BOB, U256::from(100)).unwrap_ok();
+
+        let balance_at = |token: &TestToken, account: Address, id: U256| {
+            Snapshots::balance_of_at(token, account, id).unwrap_ok()
+        };
+        assert_eq!(balance_at(&token, ALICE, first), U256::from(1_000));
+        assert_eq!(balance_at(&token, BOB, first), U256::ZERO);
+        assert_eq!(balance_at(&token, ALICE, second), U256::from(700));
+        assert_eq!(balance_at(&token, BOB, second), U256::from(300));
+        assert_eq!(balance_at(&token, CAROL, second), U256::ZERO);
+
+        assert_eq!(
+            Snapshots::total_supply_at(&token, first).unwrap_ok(),
+            U256::from(1_000)
+        );
+        assert_eq!(
+            Snapshots::total_supply_at(&token, second).unwrap_ok(),
+            U256::from(1_000)
+        );
+
+        // Snapshots taken after the last change read the current values.
+        let third = token.snapshots.snapshot();
+        assert_eq!(balance_at(&token, ALICE, third), U256::from(500));
+        assert_eq!(
+            Snapshots::total_supply_at(&token, third).unwrap_ok(),
+            U256::from(900)
+        );
+    }
+
+    #[test]
+    fn snapshot_id_zero() {
+        let host = Host::new();
+        let token = host.contract::<TestToken>();
+
+        let revert = Snapshots::total_supply_at(&token, U256::ZERO).unwrap_revert();
+
+        assert_eq!(revert, SnapshotIdZero {}.encode());
+    }
+
+    #[test]
+    fn nonexistent_snapshot() {
+        let host = Host::new();
+        let mut token = host.contract::<TestToken>();
+
+        token.snapshots.snapshot();
+
+        let revert = Snapshots::balance_of_at(&token, ALICE, U256::from(2)).unwrap_revert();
+
+        assert_eq!(
+            revert,
+            NonexistentSnapshot {
+                id: U256::from(2),
+            }
+            .encode()
+        );
+    }
+}
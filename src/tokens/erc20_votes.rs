@@ -0,0 +1,763 @@
+//! Provides a voting extension for the [`ERC20`] token.
+//!
+//! The [`ERC20Votes`] type keeps a history of each account's voting power,
+//! which holders assign by delegating, either to themselves or to another
+//! account. It implements the OpenZeppelin `IVotes` interface and ERC-6372,
+//! so existing Governor contracts and front ends can read it.
+//!
+//! Votes follow every balance change through the [`ERC20Params`] transfer hooks,
+//! so the token's params forward [`ERC20Params::after_token_transfer`] to
+//! [`ERC20Votes::after_token_transfer`], and [`ERC20Votes`] is inherited
+//! alongside [`ERC20`]:
+//!
+//! ```ignore
+//! impl ERC20Params for MyParams {
+//!     // ...
+//!
+//!     fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC20<Self>>>(
+//!         storage: &mut S,
+//!         from: Address,
+//!         to: Address,
+//!         amount: U256,
+//!     ) -> Result<(), Vec<u8>> {
+//!         Ok(ERC20Votes::<Self>::after_token_transfer(storage, from, to, amount)?)
+//!     }
+//! }
+//!
+//! sol_storage! {
+//!     #[entrypoint]
+//!     pub struct MyToken {
+//!         #[borrow]
+//!         ERC20<MyParams> erc20;
+//!         #[borrow]
+//!         ERC20Votes<MyParams> votes;
+//!     }
+//! }
+//!
+//! #[external]
+//! #[inherit(ERC20Votes<MyParams>, ERC20<MyParams>)]
+//! impl MyToken {}
+//! ```
+//!
+//! The delegates and checkpoints live in the `rustmate.ERC20Votes` namespace,
+//! see [`crate::utils::namespace`].
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use crate::host::{
+    block,
+    msg,
+};
+use crate::tokens::erc20::{
+    ERC20Error,
+    ERC20Params,
+    ERC20,
+};
+use crate::utils::{
+    eip712,
+    namespace,
+};
+use alloc::{
+    string::String,
+    vec::Vec,
+};
+use alloy_primitives::{
+    b256,
+    Address,
+    B256,
+    U256,
+    U64,
+};
+use alloy_sol_types::{
+    sol,
+    SolError,
+};
+use core::{
+    borrow::BorrowMut,
+    marker::PhantomData,
+};
+use stylus_sdk::{
+    evm,
+    prelude::*,
+    storage::StorageVec,
+};
+
+sol_storage! {
+    /// ERC20Votes exposes the voting power delegated by [`ERC20`] holders
+    pub struct ERC20Votes<T: ERC20Params> {
+        PhantomData<T> phantom;
+    }
+
+    /// The delegates and checkpoints, kept in the `rustmate.ERC20Votes` namespace
+    pub struct VotesStorage {
+        mapping(address => address) delegates;
+        mapping(address => Checkpoint[]) checkpoints;
+        Checkpoint[] total_supply_checkpoints;
+    }
+
+    /// The value of a voting quantity from the block `key` onwards
+    pub struct Checkpoint {
+        uint64 key;
+        uint256 value;
+    }
+}
+
+/// keccak256(abi.encode(uint256(keccak256("rustmate.ERC20Votes")) - 1)) & ~bytes32(uint256(0xff))
+const STORAGE_SLOT: B256 =
+    b256!("eb2670fa28c6bfa2759f2a04cf0c30ab41f7cba7519e49b58d207c4ea90bbf00");
+
+// Declare events and Solidity error types
+sol! {
+    event DelegateChanged(address indexed delegator, address indexed fromDelegate, address indexed toDelegate);
+    event DelegateVotesChanged(address indexed delegate, uint256 previousVotes, uint256 newVotes);
+
+    error VotesExpiredSignature(uint256 expiry);
+    error InvalidAccountNonce(address account, uint256 currentNonce);
+    error ERC5805FutureLookup(uint256 timepoint, uint48 clock);
+    error VotesUnderflow(address delegate, uint256 votes, uint256 needed);
+    error VotesOverflow(address delegate, uint256 votes, uint256 added);
+}
+
+// Declare the EIP-712 message signed to delegate
+sol! {
+    struct Delegation {
+        address delegatee;
+        uint256 nonce;
+        uint256 expiry;
+    }
+}
+
+/// Represents the ways methods may fail.
+pub enum ERC20VotesError {
+    VotesExpiredSignature(VotesExpiredSignature),
+    InvalidAccountNonce(InvalidAccountNonce),
+    ERC5805FutureLookup(ERC5805FutureLookup),
+    VotesUnderflow(VotesUnderflow),
+    VotesOverflow(VotesOverflow),
+    ERC20(ERC20Error),
+}
+
+impl From<ERC20Error> for ERC20VotesError {
+    fn from(err: ERC20Error) -> Self {
+        Self::ERC20(err)
+    }
+}
+
+/// We will soon provide a `#[derive(SolidityError)]` to clean this up.
+impl From<ERC20VotesError> for Vec<u8> {
+    fn from(val: ERC20VotesError) -> Self {
+        match val {
+            ERC20VotesError::VotesExpiredSignature(err) => err.encode(),
+            ERC20VotesError::InvalidAccountNonce(err) => err.encode(),
+            ERC20VotesError::ERC5805FutureLookup(err) => err.encode(),
+            ERC20VotesError::VotesUnderflow(err) => err.encode(),
+            ERC20VotesError::VotesOverflow(err) => err.encode(),
+            ERC20VotesError::ERC20(err) => err.into(),
+        }
+    }
+}
+
+/// Simplifies the result type for the contract's methods.
+type Result<T, E = ERC20VotesError> = core::result::Result<T, E>;
+
+/// Returns the latest value in `checkpoints`, or zero if there is none.
+fn latest(checkpoints: &StorageVec<Checkpoint>) -> U256 {
+    match checkpoints.len() {
+        0 => U256::ZERO,
+        len => checkpoints.getter(len - 1).unwrap().value.get(),
+    }
+}
+
+/// Returns the value in `checkpoints` as of the block `key`.
+fn upper_lookup(checkpoints: &StorageVec<Checkpoint>, key: u64) -> U256 {
+    // Find the first checkpoint after `key`; the one before it was in effect.
+    let (mut low, mut high) = (0, checkpoints.len());
+    while low < high {
+        let mid = (low + high) / 2;
+        if checkpoints.getter(mid).unwrap().key.get() > U64::from(key) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+
+    match high {
+        0 => U256::ZERO,
+        high => checkpoints.getter(high - 1).unwrap().value.get(),
+    }
+}
+
+/// Records `value` as of the current block, returning the previous latest value.
+fn push(checkpoints: &mut StorageVec<Checkpoint>, value: U256) -> U256 {
+    let key = U64::from(block::number());
+    let len = checkpoints.len();
+
+    if len > 0 {
+        let mut last = checkpoints.setter(len - 1).unwrap();
+        if last.key.get() == key {
+            let previous = last.value.get();
+            last.value.set(value);
+            return previous;
+        }
+    }
+
+    let previous = latest(checkpoints);
+    let mut checkpoint = checkpoints.grow();
+    checkpoint.key.set(key);
+    checkpoint.value.set(value);
+    previous
+}
+
+impl VotesStorage {
+    /// Returns the votes of the token in its namespace.
+    fn load() -> Self {
+        namespace::load(STORAGE_SLOT)
+    }
+
+    fn move_delegate_votes(&mut self, from: Address, to: Address, amount: U256) -> Result<()> {
+        if from == to || amount == U256::ZERO {
+            return Ok(());
+        }
+
+        if !from.is_zero() {
+            let mut checkpoints = self.checkpoints.setter(from);
+            let previous = latest(&checkpoints);
+            let votes = previous.checked_sub(amount).ok_or(
+                ERC20VotesError::VotesUnderflow(VotesUnderflow {
+                    delegate: from,
+                    votes: previous,
+                    needed: amount,
+                }),
+            )?;
+            push(&mut checkpoints, votes);
+
+            evm::log(DelegateVotesChanged {
+                delegate: from,
+                previousVotes: previous,
+                newVotes: votes,
+            });
+        }
+
+        if !to.is_zero() {
+            let mut checkpoints = self.checkpoints.setter(to);
+            let previous = latest(&checkpoints);
+            let votes = previous.checked_add(amount).ok_or(
+                ERC20VotesError::VotesOverflow(VotesOverflow {
+                    delegate: to,
+                    votes: previous,
+                    added: amount,
+                }),
+            )?;
+            push(&mut checkpoints, votes);
+
+            evm::log(DelegateVotesChanged {
+                delegate: to,
+                previousVotes: previous,
+                newVotes: votes,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: ERC20Params> ERC20Votes<T> {
+    /// Moves the votes backing `amount` tokens once they moved from `from` to `to`.
+    /// Call it from [`ERC20Params::after_token_transfer`], so that it sees mints,
+    /// burns and every kind of transfer.
+    pub fn after_token_transfer<S>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        amount: U256,
+    ) -> Result<()>
+    where
+        S: TopLevelStorage + BorrowMut<ERC20<T>>,
+    {
+        let mut votes = VotesStorage::load();
+
+        if from.is_zero() || to.is_zero() {
+            let total_supply = storage.borrow_mut().total_supply.get();
+            push(&mut votes.total_supply_checkpoints, total_supply);
+        }
+
+        let from = votes.delegates.get(from);
+        let to = votes.delegates.get(to);
+        votes.move_delegate_votes(from, to, amount)
+    }
+
+    fn delegate_votes<S>(storage: &mut S, account: Address, delegatee: Address) -> Result<()>
+    where
+        S: TopLevelStorage + BorrowMut<ERC20<T>>,
+    {
+        let balance = storage.borrow_mut().balance.get(account);
+        let mut votes = VotesStorage::load();
+
+        let previous = votes.delegates.replace(account, delegatee);
+
+        evm::log(DelegateChanged {
+            delegator: account,
+            fromDelegate: previous,
+            toDelegate: delegatee,
+        });
+
+        votes.move_delegate_votes(previous, delegatee, balance)
+    }
+
+    fn check_past(timepoint: U256) -> Result<u64> {
+        let clock = block::number();
+
+        if timepoint >= U256::from(clock) {
+            return Err(ERC20VotesError::ERC5805FutureLookup(ERC5805FutureLookup {
+                timepoint,
+                clock,
+            }));
+        }
+
+        Ok(timepoint.to())
+    }
+}
+
+#[external]
+#[inherit(ERC20<T>)]
+impl<T: ERC20Params> ERC20Votes<T> {
+    pub fn clock() -> Result<u64> {
+        Ok(block::number())
+    }
+
+    #[selector(name = "CLOCK_MODE")]
+    pub fn clock_mode() -> Result<String> {
+        Ok("mode=blocknumber&from=default".into())
+    }
+
+    pub fn delegates(&self, account: Address) -> Result<Address> {
+        Ok(VotesStorage::load().delegates.get(account))
+    }
+
+    pub fn num_checkpoints(&self, account: Address) -> Result<u32> {
+        Ok(VotesStorage::load().checkpoints.getter(account).len() as u32)
+    }
+
+    /// Returns the block and votes of an account's checkpoint, or zeros if it does not exist.
+    pub fn checkpoints(&self, account: Address, pos: u32) -> Result<(u64, U256)> {
+        let votes = VotesStorage::load();
+        let checkpoints = votes.checkpoints.getter(account);
+
+        Ok(checkpoints
+            .getter(pos)
+            .map(|checkpoint| (checkpoint.key.get().to(), checkpoint.value.get()))
+            .unwrap_or_default())
+    }
+
+    pub fn get_votes(&self, account: Address) -> Result<U256> {
+        Ok(latest(&VotesStorage::load().checkpoints.getter(account)))
+    }
+
+    pub fn get_past_votes(&self, account: Address, timepoint: U256) -> Result<U256> {
+        let timepoint = Self::check_past(timepoint)?;
+        Ok(upper_lookup(&VotesStorage::load().checkpoints.getter(account), timepoint))
+    }
+
+    pub fn get_past_total_supply(&self, timepoint: U256) -> Result<U256> {
+        let timepoint = Self::check_past(timepoint)?;
+        Ok(upper_lookup(&VotesStorage::load().total_supply_checkpoints, timepoint))
+    }
+
+    pub fn delegate<S>(storage: &mut S, delegatee: Address) -> Result<()>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC20<T>>,
+    {
+        Self::delegate_votes(storage, msg::sender(), delegatee)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn delegate_by_sig<S>(
+        storage: &mut S,
+        delegatee: Address,
+        nonce: U256,
+        expiry: U256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> Result<()>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC20<T>>,
+    {
+        if U256::from(block::timestamp()) > expiry {
+            return Err(ERC20VotesError::VotesExpiredSignature(
+                VotesExpiredSignature { expiry },
+            ));
+        }
+
+        let struct_hash = eip712::hash_struct(&Delegation {
+            delegatee,
+            nonce,
+            expiry,
+        });
+
+        let token: &mut ERC20<T> = storage.borrow_mut();
+        let digest = token.hash_typed_data(struct_hash)?;
+        let signer = ERC20::<T>::recover(digest, v, r, s)?;
+
+        // Shares the permit nonces, so a signature can only be used once.
+        let mut nonce_setter = token.nonces.setter(signer);
+        let current_nonce = nonce_setter.get();
+
+        if nonce != current_nonce {
+            return Err(ERC20VotesError::InvalidAccountNonce(InvalidAccountNonce {
+                account: signer,
+                currentNonce: current_nonce,
+            }));
+        }
+
+        nonce_setter.set(current_nonce + U256::from(1));
+
+        Self::delegate_votes(storage, signer, delegatee)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        sign,
+        signer,
+        Host,
+        ResultExt,
+        ALICE,
+        BOB,
+        CAROL,
+    };
+    use alloy_primitives::{
+        b256,
+        keccak256,
+    };
+    use alloy_sol_types::SolType;
+
+    struct TestParams;
+
+    impl ERC20Params for TestParams {
+        const NAME: &'static str = "Vote Token";
+        const SYMBOL: &'static str = "VOTE";
+        const DECIMALS: u8 = 18;
+        const INITIAL_CHAIN_ID: u64 = 1;
+        const INITIAL_DOMAIN_SEPARATOR: B256 = B256::repeat_byte(0xde);
+
+        fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC20<Self>>>(
+            storage: &mut S,
+            from: Address,
+            to: Address,
+            amount: U256,
+        ) -> Result<(), Vec<u8>> {
+            Ok(ERC20Votes::<Self>::after_token_transfer(storage, from, to, amount)?)
+        }
+    }
+
+    sol_storage! {
+        pub struct TestToken {
+            #[borrow]
+            ERC20<TestParams> erc20;
+            #[borrow]
+            ERC20Votes<TestParams> votes;
+        }
+    }
+
+    unsafe impl TopLevelStorage for TestToken {}
+
+    type Votes = ERC20Votes<TestParams>;
+
+    fn setup() -> (Host, TestToken) {
+        let host = Host::new();
+        host.set_chain_id(1);
+        host.set_block_number(1);
+        let token = host.contract::<TestToken>();
+        (host, token)
+    }
+
+    #[test]
+    fn clock() {
+        let (host, _token) = setup();
+
+        host.set_block_number(42);
+
+        assert_eq!(Votes::clock().unwrap_ok(), 42);
+        assert_eq!(Votes::clock_mode().unwrap_ok(), "mode=blocknumber&from=default");
+    }
+
+    #[test]
+    fn delegate() {
+        let (host, mut token) = setup();
+
+        ERC20::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+        assert_eq!(token.votes.get_votes(ALICE).unwrap_ok(), U256::ZERO);
+
+        host.set_sender(ALICE);
+        Votes::delegate(&mut token, BOB).unwrap_ok();
+
+        assert_eq!(token.votes.delegates(ALICE).unwrap_ok(), BOB);
+        assert_eq!(token.votes.get_votes(BOB).unwrap_ok(), U256::from(1_000));
+        assert!(host.emitted(&DelegateChanged {
+            delegator: ALICE,
+            fromDelegate: Address::ZERO,
+            toDelegate: BOB,
+        }));
+        assert!(host.emitted(&DelegateVotesChanged {
+            delegate: BOB,
+            previousVotes: U256::ZERO,
+            newVotes: U256::from(1_000),
+        }));
+    }
+
+    #[test]
+    fn transfers_move_votes() {
+        let (host, mut token) = setup();
+
+        ERC20::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+        host.set_sender(ALICE);
+        Votes::delegate(&mut token, ALICE).unwrap_ok();
+        host.set_sender(BOB);
+        Votes::delegate(&mut token, CAROL).unwrap_ok();
+
+        host.set_sender(ALICE);
+        ERC20::transfer(&mut token, BOB, U256::from(300)).unwrap_ok();
+        token.erc20.approve(BOB, U256::from(200)).unwrap_ok();
+        host.set_sender(BOB);
+        ERC20::transfer_from(&mut token, ALICE, BOB, U256::from(200)).unwrap_ok();
+        ERC20::burn(&mut token, BOB, U256::from(100)).unwrap_ok();
+
+        assert_eq!(token.votes.get_votes(ALICE).unwrap_ok(), U256::from(500));
+        assert_eq!(token.votes.get_votes(CAROL).unwrap_ok(), U256::from(400));
+        assert_eq!(token.votes.get_votes(BOB).unwrap_ok(), U256::ZERO);
+    }
+
+    // Votes used to be moved by wrappers only, so minting through ERC20 left them
+    // stale and the next transfer wrapped them around.
+    #[test]
+    fn every_balance_change_moves_votes() {
+        let (host, mut token) = setup();
+
+        ERC20::mint(&mut token, ALICE, U256::from(100)).unwrap_ok();
+        host.set_sender(ALICE);
+        Votes::delegate(&mut token, ALICE).unwrap_ok();
+
+        ERC20::mint(&mut token, ALICE, U256::from(50)).unwrap_ok();
+        assert_eq!(token.votes.get_votes(ALICE).unwrap_ok(), U256::from(150));
+
+        ERC20::transfer(&mut token, BOB, U256::from(150)).unwrap_ok();
+        assert_eq!(token.votes.get_votes(ALICE).unwrap_ok(), U256::ZERO);
+    }
+
+    #[test]
+    fn votes_underflow() {
+        let (_host, _token) = setup();
+
+        let revert = VotesStorage::load()
+            .move_delegate_votes(ALICE, Address::ZERO, U256::from(1))
+            .unwrap_revert();
+
+        assert_eq!(
+            revert,
+            VotesUnderflow {
+                delegate: ALICE,
+                votes: U256::ZERO,
+                needed: U256::from(1),
+            }
+            .encode()
+        );
+    }
+
+    #[test]
+    fn past_votes() {
+        let (host, mut token) = setup();
+
+        host.set_sender(ALICE);
+        Votes::delegate(&mut token, ALICE).unwrap_ok();
+
+        host.set_block_number(10);
+        ERC20::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+        host.set_block_number(20);
+        ERC20::transfer(&mut token, BOB, U256::from(400)).unwrap_ok();
+        ERC20::transfer(&mut token, BOB, U256::from(100)).unwrap_ok();
+        host.set_block_number(30);
+
+        let past_votes = |token: &TestToken, block: u64| {
+            token.votes.get_past_votes(ALICE, U256::from(block)).unwrap_ok()
+        };
+        assert_eq!(past_votes(&token, 9), U256::ZERO);
+        assert_eq!(past_votes(&token, 10), U256::from(1_000));
+        assert_eq!(past_votes(&token, 19), U256::from(1_000));
+        assert_eq!(past_votes(&token, 20), U256::from(500));
+        assert_eq!(past_votes(&token, 29), U256::from(500));
+
+        // Both transfers in block 20 share one checkpoint.
+        assert_eq!(token.votes.num_checkpoints(ALICE).unwrap_ok(), 2);
+        assert_eq!(
+            token.votes.checkpoints(ALICE, 1).unwrap_ok(),
+            (20, U256::from(500))
+        );
+    }
+
+    #[test]
+    fn past_total_supply() {
+        let (host, mut token) = setup();
+
+        host.set_block_number(10);
+        ERC20::mint(&mut token, ALICE, U256::from(1_000)).unwrap_ok();
+        host.set_block_number(20);
+        ERC20::burn(&mut token, ALICE, U256::from(400)).unwrap_ok();
+        host.set_block_number(30);
+
+        let past_total_supply = |token: &TestToken, block: u64| {
+            token.votes.get_past_total_supply(U256::from(block)).unwrap_ok()
+        };
+        assert_eq!(past_total_supply(&token, 9), U256::ZERO);
+        assert_eq!(past_total_supply(&token, 15), U256::from(1_000));
+        assert_eq!(past_total_supply(&token, 25), U256::from(600));
+    }
+
+    #[test]
+    fn future_lookup() {
+        let (host, token) = setup();
+
+        host.set_block_number(30);
+
+        let revert = token
+            .votes
+            .get_past_votes(ALICE, U256::from(30))
+            .unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ERC5805FutureLookup {
+                timepoint: U256::from(30),
+                clock: 30,
+            }
+            .encode()
+        );
+    }
+
+    const OWNER_KEY: B256 = B256::repeat_byte(0x42);
+
+    // keccak256("Delegation(address delegatee,uint256 nonce,uint256 expiry)")
+    const DELEGATION_TYPEHASH: B256 =
+        b256!("e48329057bfd03d55e49b547132e39cffd9c1820ad7b9d4c5307691425d15adf");
+
+    fn delegation_digest(delegatee: Address, nonce: U256, expiry: U256) -> B256 {
+        typed_data_digest(keccak256(<sol! { (bytes32, address, uint256, uint256) }>::encode(
+            &(*DELEGATION_TYPEHASH, delegatee, nonce, expiry),
+        )))
+    }
+
+    fn typed_data_digest(struct_hash: B256) -> B256 {
+        keccak256(
+            [
+                &[0x19, 0x01],
+                &TestParams::INITIAL_DOMAIN_SEPARATOR[..],
+                &struct_hash[..],
+            ]
+            .concat(),
+        )
+    }
+
+    #[test]
+    fn delegate_by_sig() {
+        let (_host, mut token) = setup();
+        let owner = signer(OWNER_KEY);
+
+        ERC20::mint(&mut token, owner, U256::from(1_000)).unwrap_ok();
+
+        let digest = delegation_digest(BOB, U256::ZERO, U256::MAX);
+        let (v, r, s) = sign(OWNER_KEY, digest);
+        Votes::delegate_by_sig(&mut token, BOB, U256::ZERO, U256::MAX, v, r, s).unwrap_ok();
+
+        assert_eq!(token.votes.delegates(owner).unwrap_ok(), BOB);
+        assert_eq!(token.votes.get_votes(BOB).unwrap_ok(), U256::from(1_000));
+        assert_eq!(token.erc20.nonces(owner).unwrap_ok(), U256::from(1));
+
+        let revert = Votes::delegate_by_sig(&mut token, BOB, U256::ZERO, U256::MAX, v, r, s)
+            .unwrap_revert();
+
+        assert_eq!(
+            revert,
+            InvalidAccountNonce {
+                account: owner,
+                currentNonce: U256::from(1),
+            }
+            .encode()
+        );
+    }
+
+    #[test]
+    fn delegate_by_sig_expired() {
+        let (host, mut token) = setup();
+
+        host.set_timestamp(1_000);
+
+        let revert = Votes::delegate_by_sig(
+            &mut token,
+            BOB,
+            U256::ZERO,
+            U256::from(999),
+            27,
+            B256::ZERO,
+            B256::ZERO,
+        )
+        .unwrap_revert();
+
+        assert_eq!(
+            revert,
+            VotesExpiredSignature {
+                expiry: U256::from(999),
+            }
+            .encode()
+        );
+    }
+
+    // keccak256("TransferWithAuthorization(address from,address to,uint256 value,uint256 validAfter,uint256 validBefore,bytes32 nonce)")
+    const TRANSFER_WITH_AUTHORIZATION_TYPEHASH: B256 =
+        b256!("7c7c6cdb67a18743f49ec6fa9b35f50d52ed05cbed4cc592e13b44501c1a2267");
+
+    #[test]
+    fn transfer_with_authorization_moves_votes() {
+        let (host, mut token) = setup();
+        let owner = signer(OWNER_KEY);
+
+        ERC20::mint(&mut token, owner, U256::from(1_000)).unwrap_ok();
+        host.set_sender(owner);
+        Votes::delegate(&mut token, owner).unwrap_ok();
+        host.set_sender(BOB);
+        Votes::delegate(&mut token, BOB).unwrap_ok();
+        host.set_timestamp(1_000);
+
+        let nonce = B256::repeat_byte(0x99);
+        let struct_hash = keccak256(
+            <sol! { (bytes32, address, address, uint256, uint256, uint256, bytes32) }>::encode(&(
+                *TRANSFER_WITH_AUTHORIZATION_TYPEHASH,
+                owner,
+                BOB,
+                U256::from(400),
+                U256::ZERO,
+                U256::MAX,
+                *nonce,
+            )),
+        );
+        let (v, r, s) = sign(OWNER_KEY, typed_data_digest(struct_hash));
+
+        ERC20::transfer_with_authorization(
+            &mut token,
+            owner,
+            BOB,
+            U256::from(400),
+            U256::ZERO,
+            U256::MAX,
+            nonce,
+            v,
+            r,
+            s,
+        )
+        .unwrap_ok();
+
+        assert_eq!(token.votes.get_votes(owner).unwrap_ok(), U256::from(600));
+        assert_eq!(token.votes.get_votes(BOB).unwrap_ok(), U256::from(400));
+    }
+}
@@ -0,0 +1,347 @@
+//! Provides an implementation of the ERC-4907 rental extension for the [`ERC721`] token.
+//!
+//! The [`ERC4907`] type lets the owner of a token, or one of their operators,
+//! grant a user role that expires on its own, without moving ownership. The
+//! user is cleared whenever the token changes hands or is burned, which the
+//! token's params report by forwarding [`ERC721Params::after_token_transfer`] to
+//! [`ERC4907::after_token_transfer`].
+//!
+//! [`ERC4907`] is inherited alongside [`ERC721`], and the params set
+//! [`ERC721Params::RENTABLE`] so that `supportsInterface` reports it:
+//!
+//! ```ignore
+//! impl ERC721Params for MyParams {
+//!     // ...
+//!
+//!     const RENTABLE: bool = true;
+//!
+//!     fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+//!         _storage: &mut S,
+//!         from: Address,
+//!         to: Address,
+//!         first_id: U256,
+//!         _batch_size: U256,
+//!     ) -> Result<(), Vec<u8>> {
+//!         ERC4907::<Self>::after_token_transfer(from, to, first_id);
+//!         Ok(())
+//!     }
+//! }
+//!
+//! sol_storage! {
+//!     #[entrypoint]
+//!     pub struct MyNFT {
+//!         #[borrow]
+//!         ERC721<MyParams> erc721;
+//!         #[borrow]
+//!         ERC4907<MyParams> rentable;
+//!     }
+//! }
+//!
+//! #[external]
+//! #[inherit(ERC4907<MyParams>, ERC721<MyParams>)]
+//! impl MyNFT {}
+//! ```
+//!
+//! The users live in the `rustmate.ERC4907` namespace, see
+//! [`crate::utils::namespace`].
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use crate::host::{
+    block,
+    msg,
+};
+use crate::tokens::erc721::{
+    ERC721Error,
+    ERC721Params,
+    NotAuthorized,
+    ERC721,
+};
+use crate::utils::namespace;
+use alloy_primitives::{
+    b256,
+    Address,
+    B256,
+    U256,
+    U64,
+};
+use alloy_sol_types::sol;
+use core::{
+    borrow::BorrowMut,
+    marker::PhantomData,
+};
+use stylus_sdk::{
+    evm,
+    prelude::*,
+};
+
+sol_storage! {
+    /// ERC4907 stores the user of each token of an [`ERC721`]
+    pub struct ERC4907<T: ERC721Params> {
+        PhantomData<T> phantom;
+    }
+
+    /// The users, kept in the `rustmate.ERC4907` namespace
+    pub struct RentalStorage {
+        mapping(uint256 => UserInfo) users;
+    }
+
+    /// A token user and the timestamp their role expires at
+    pub struct UserInfo {
+        address user;
+        uint64 expires;
+    }
+}
+
+/// keccak256(abi.encode(uint256(keccak256("rustmate.ERC4907")) - 1)) & ~bytes32(uint256(0xff))
+const STORAGE_SLOT: B256 =
+    b256!("68779e70eb2ff9adb412a3f4077b7a1f50b198ea37a71e95441fdcd0b8a61b00");
+
+// Declare events
+sol! {
+    event UpdateUser(uint256 indexed tokenId, address indexed user, uint64 expires);
+}
+
+/// Simplifies the result type for the contract's methods.
+type Result<T, E = ERC721Error> = core::result::Result<T, E>;
+
+impl RentalStorage {
+    /// Returns the users of the token in their namespace.
+    fn load() -> Self {
+        namespace::load(STORAGE_SLOT)
+    }
+
+    /// Removes the user of `id`, if any.
+    fn clear_user(&mut self, id: U256) {
+        let mut info = self.users.setter(id);
+
+        if info.user.get().is_zero() {
+            return;
+        }
+
+        info.user.set(Address::ZERO);
+        info.expires.set(U64::ZERO);
+
+        evm::log(UpdateUser {
+            tokenId: id,
+            user: Address::ZERO,
+            expires: 0,
+        });
+    }
+}
+
+impl<T: ERC721Params> ERC4907<T> {
+    /// Clears the user of a token that changed hands or was burned. Call it from
+    /// [`ERC721Params::after_token_transfer`]. Minted tokens have no user to clear,
+    /// so batches are skipped.
+    pub fn after_token_transfer(from: Address, to: Address, id: U256) {
+        if !from.is_zero() && from != to {
+            RentalStorage::load().clear_user(id);
+        }
+    }
+}
+
+#[external]
+#[inherit(ERC721<T>)]
+impl<T: ERC721Params> ERC4907<T> {
+    pub fn set_user<S>(storage: &mut S, id: U256, user: Address, expires: u64) -> Result<()>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC721<T>>,
+    {
+        let token: &mut ERC721<T> = storage.borrow_mut();
+        let owner = token.owner_of(id)?;
+
+        if msg::sender() != owner && !token.is_approved_for_all.getter(owner).get(msg::sender()) {
+            return Err(ERC721Error::NotAuthorized(NotAuthorized {}));
+        }
+
+        let mut rentals = RentalStorage::load();
+        let mut info = rentals.users.setter(id);
+        info.user.set(user);
+        info.expires.set(U64::from(expires));
+
+        evm::log(UpdateUser {
+            tokenId: id,
+            user,
+            expires,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the user of `id`, or zero if there is none or their role has expired.
+    pub fn user_of(&self, id: U256) -> Result<Address> {
+        let rentals = RentalStorage::load();
+        let info = rentals.users.getter(id);
+
+        if info.expires.get() < U64::from(block::timestamp()) {
+            return Ok(Address::ZERO);
+        }
+
+        Ok(info.user.get())
+    }
+
+    pub fn user_expires(&self, id: U256) -> Result<U256> {
+        let rentals = RentalStorage::load();
+        Ok(U256::from(rentals.users.getter(id).expires.get()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        Host,
+        ResultExt,
+        ALICE,
+        BOB,
+        CAROL,
+    };
+    use crate::tokens::erc721::NotMinted;
+    use alloc::{
+        string::String,
+        vec::Vec,
+    };
+    use alloy_sol_types::SolError;
+
+    struct TestParams;
+
+    impl ERC721Params for TestParams {
+        const NAME: &'static str = "Test NFT";
+        const SYMBOL: &'static str = "TNFT";
+        const RENTABLE: bool = true;
+
+        fn token_uri(_id: U256) -> String {
+            String::new()
+        }
+
+        fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+            _storage: &mut S,
+            from: Address,
+            to: Address,
+            first_id: U256,
+            _batch_size: U256,
+        ) -> Result<(), Vec<u8>> {
+            Rentable::after_token_transfer(from, to, first_id);
+            Ok(())
+        }
+    }
+
+    sol_storage! {
+        pub struct TestNFT {
+            #[borrow]
+            ERC721<TestParams> erc721;
+            #[borrow]
+            ERC4907<TestParams> rentable;
+        }
+    }
+
+    unsafe impl TopLevelStorage for TestNFT {}
+
+    type Rentable = ERC4907<TestParams>;
+    type Token = ERC721<TestParams>;
+
+    const ID: U256 = U256::from_limbs([1, 0, 0, 0]);
+
+    fn setup() -> (Host, TestNFT) {
+        let host = Host::new();
+        let mut nft = host.contract::<TestNFT>();
+        Token::mint(&mut nft, ALICE, ID).unwrap_ok();
+        host.set_timestamp(1_000);
+        host.set_sender(ALICE);
+        (host, nft)
+    }
+
+    #[test]
+    fn set_user() {
+        let (host, mut nft) = setup();
+
+        Rentable::set_user(&mut nft, ID, BOB, 2_000).unwrap_ok();
+
+        assert_eq!(nft.rentable.user_of(ID).unwrap_ok(), BOB);
+        assert_eq!(nft.rentable.user_expires(ID).unwrap_ok(), U256::from(2_000));
+        assert!(host.emitted(&UpdateUser {
+            tokenId: ID,
+            user: BOB,
+            expires: 2_000,
+        }));
+
+        // The role lasts until the expiry timestamp included.
+        host.set_timestamp(2_000);
+        assert_eq!(nft.rentable.user_of(ID).unwrap_ok(), BOB);
+
+        host.set_timestamp(2_001);
+        assert_eq!(nft.rentable.user_of(ID).unwrap_ok(), Address::ZERO);
+        assert_eq!(nft.rentable.user_expires(ID).unwrap_ok(), U256::from(2_000));
+    }
+
+    #[test]
+    fn set_user_operator() {
+        let (host, mut nft) = setup();
+        nft.erc721.set_approval_for_all(CAROL, true).unwrap_ok();
+
+        host.set_sender(CAROL);
+        Rentable::set_user(&mut nft, ID, BOB, 2_000).unwrap_ok();
+
+        assert_eq!(nft.rentable.user_of(ID).unwrap_ok(), BOB);
+    }
+
+    #[test]
+    fn set_user_unauthorized() {
+        let (host, mut nft) = setup();
+
+        host.set_sender(BOB);
+        let revert = Rentable::set_user(&mut nft, ID, BOB, 2_000).unwrap_revert();
+        assert_eq!(revert, NotAuthorized {}.encode());
+
+        let revert = Rentable::set_user(&mut nft, U256::from(2), BOB, 2_000).unwrap_revert();
+        assert_eq!(revert, NotMinted {}.encode());
+    }
+
+    #[test]
+    fn transfer_clears_user() {
+        let (host, mut nft) = setup();
+        Rentable::set_user(&mut nft, ID, BOB, 2_000).unwrap_ok();
+
+        Token::transfer_from(&mut nft, ALICE, CAROL, ID).unwrap_ok();
+
+        assert_eq!(nft.erc721.owner_of(ID).unwrap_ok(), CAROL);
+        assert_eq!(nft.rentable.user_of(ID).unwrap_ok(), Address::ZERO);
+        assert_eq!(nft.rentable.user_expires(ID).unwrap_ok(), U256::ZERO);
+        assert!(host.emitted(&UpdateUser {
+            tokenId: ID,
+            user: Address::ZERO,
+            expires: 0,
+        }));
+    }
+
+    #[test]
+    fn safe_transfer_by_operator_clears_user() {
+        let (host, mut nft) = setup();
+        Rentable::set_user(&mut nft, ID, BOB, 2_000).unwrap_ok();
+        nft.erc721.set_approval_for_all(CAROL, true).unwrap_ok();
+
+        host.set_sender(CAROL);
+        Token::safe_transfer_from(&mut nft, ALICE, CAROL, ID).unwrap_ok();
+
+        assert_eq!(nft.rentable.user_of(ID).unwrap_ok(), Address::ZERO);
+    }
+
+    #[test]
+    fn burn_clears_user() {
+        let (_host, mut nft) = setup();
+        Rentable::set_user(&mut nft, ID, BOB, 2_000).unwrap_ok();
+
+        Token::burn(&mut nft, ID).unwrap_ok();
+        Token::mint(&mut nft, CAROL, ID).unwrap_ok();
+
+        assert_eq!(nft.rentable.user_of(ID).unwrap_ok(), Address::ZERO);
+    }
+
+    #[test]
+    fn supports_interface() {
+        assert!(Token::supports_interface(0xad092b5cu32.to_be_bytes()).unwrap_ok());
+        assert!(Token::supports_interface(0x80ac58cdu32.to_be_bytes()).unwrap_ok());
+        assert!(!Token::supports_interface(0x780e9d63u32.to_be_bytes()).unwrap_ok());
+    }
+}
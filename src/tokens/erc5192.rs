@@ -0,0 +1,386 @@
+//! Provides an implementation of the ERC-5192 soulbound extension for the [`ERC721`] token.
+//!
+//! The [`ERC5192`] type lets the inheriting contract lock tokens to their owner.
+//! A locked token cannot be transferred or approved, by `approve` or by `permit`,
+//! but can still be burned. The token's params enforce this by forwarding
+//! [`ERC721Params::before_token_transfer`] and [`ERC721Params::before_approve`] to
+//! the hooks of the same name on [`ERC5192`].
+//!
+//! [`ERC5192`] is inherited alongside [`ERC721`], and the params set
+//! [`ERC721Params::SOULBOUND`] so that `supportsInterface` reports it:
+//!
+//! ```ignore
+//! impl ERC721Params for MyParams {
+//!     // ...
+//!
+//!     const SOULBOUND: bool = true;
+//!
+//!     fn before_token_transfer<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+//!         _storage: &mut S,
+//!         from: Address,
+//!         to: Address,
+//!         first_id: U256,
+//!         _batch_size: U256,
+//!     ) -> Result<(), Vec<u8>> {
+//!         Ok(ERC5192::<Self>::before_token_transfer(from, to, first_id)?)
+//!     }
+//!
+//!     fn before_approve<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+//!         _storage: &mut S,
+//!         _owner: Address,
+//!         _spender: Address,
+//!         id: U256,
+//!     ) -> Result<(), Vec<u8>> {
+//!         Ok(ERC5192::<Self>::before_approve(id)?)
+//!     }
+//! }
+//!
+//! sol_storage! {
+//!     #[entrypoint]
+//!     pub struct MyNFT {
+//!         #[borrow]
+//!         ERC721<MyParams> erc721;
+//!         #[borrow]
+//!         ERC5192<MyParams> soulbound;
+//!     }
+//! }
+//!
+//! #[external]
+//! #[inherit(ERC5192<MyParams>, ERC721<MyParams>)]
+//! impl MyNFT {}
+//! ```
+//!
+//! Tokens are only locked and unlocked through the inherent methods, so the
+//! inheriting contract decides who may do so. A burned token loses its lock, so
+//! that it is minted again unlocked. The locks live in the `rustmate.ERC5192`
+//! namespace, see [`crate::utils::namespace`].
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use crate::tokens::erc721::{
+    ERC721Error,
+    ERC721Params,
+    ERC721,
+};
+use crate::utils::namespace;
+use alloc::vec::Vec;
+use alloy_primitives::{
+    b256,
+    Address,
+    B256,
+    U256,
+};
+use alloy_sol_types::{
+    sol,
+    SolError,
+};
+use core::{
+    borrow::{
+        Borrow,
+        BorrowMut,
+    },
+    marker::PhantomData,
+};
+use stylus_sdk::{
+    evm,
+    prelude::*,
+};
+
+sol_storage! {
+    /// ERC5192 stores which tokens of an [`ERC721`] are locked to their owner
+    pub struct ERC5192<T: ERC721Params> {
+        PhantomData<T> phantom;
+    }
+
+    /// The locks, kept in the `rustmate.ERC5192` namespace
+    pub struct SoulboundStorage {
+        mapping(uint256 => bool) locked;
+    }
+}
+
+/// keccak256(abi.encode(uint256(keccak256("rustmate.ERC5192")) - 1)) & ~bytes32(uint256(0xff))
+const STORAGE_SLOT: B256 =
+    b256!("9a7706354d73481268bb8f33b5c8a5081624720b761d85c8277b3137a3d1bd00");
+
+// Declare events and Solidity error types
+sol! {
+    event Locked(uint256 tokenId);
+    event Unlocked(uint256 tokenId);
+
+    error TokenLocked(uint256 tokenId);
+}
+
+/// Represents the ways methods may fail.
+pub enum ERC5192Error {
+    TokenLocked(TokenLocked),
+    ERC721(ERC721Error),
+}
+
+impl From<ERC721Error> for ERC5192Error {
+    fn from(err: ERC721Error) -> Self {
+        Self::ERC721(err)
+    }
+}
+
+/// We will soon provide a `#[derive(SolidityError)]` to clean this up.
+impl From<ERC5192Error> for Vec<u8> {
+    fn from(val: ERC5192Error) -> Self {
+        match val {
+            ERC5192Error::TokenLocked(err) => err.encode(),
+            ERC5192Error::ERC721(err) => err.into(),
+        }
+    }
+}
+
+/// Simplifies the result type for the contract's methods.
+type Result<T, E = ERC5192Error> = core::result::Result<T, E>;
+
+impl SoulboundStorage {
+    /// Returns the locks of the token in their namespace.
+    fn load() -> Self {
+        namespace::load(STORAGE_SLOT)
+    }
+
+    fn ensure_unlocked(&self, id: U256) -> Result<()> {
+        if self.locked.get(id) {
+            return Err(ERC5192Error::TokenLocked(TokenLocked { tokenId: id }));
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: ERC721Params> ERC5192<T> {
+    /// Locks `id` to its owner, which must exist.
+    pub fn lock<S>(storage: &mut S, id: U256) -> Result<()>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC721<T>>,
+    {
+        Borrow::<ERC721<T>>::borrow(storage).owner_of(id)?;
+
+        let mut locks = SoulboundStorage::load();
+        if !locks.locked.get(id) {
+            locks.locked.insert(id, true);
+            evm::log(Locked { tokenId: id });
+        }
+
+        Ok(())
+    }
+
+    /// Lets `id` be transferred and approved again.
+    pub fn unlock<S>(storage: &mut S, id: U256) -> Result<()>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC721<T>>,
+    {
+        Borrow::<ERC721<T>>::borrow(storage).owner_of(id)?;
+
+        let mut locks = SoulboundStorage::load();
+        if locks.locked.get(id) {
+            locks.locked.delete(id);
+            evm::log(Unlocked { tokenId: id });
+        }
+
+        Ok(())
+    }
+
+    /// Reverts if a locked token is about to be transferred, and drops the lock of
+    /// a token about to be burned. Call it from [`ERC721Params::before_token_transfer`].
+    pub fn before_token_transfer(from: Address, to: Address, id: U256) -> Result<()> {
+        let mut locks = SoulboundStorage::load();
+
+        if to.is_zero() {
+            locks.locked.delete(id);
+        } else if !from.is_zero() {
+            locks.ensure_unlocked(id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reverts if `id` is locked. Call it from [`ERC721Params::before_approve`].
+    pub fn before_approve(id: U256) -> Result<()> {
+        SoulboundStorage::load().ensure_unlocked(id)
+    }
+}
+
+#[external]
+#[inherit(ERC721<T>)]
+impl<T: ERC721Params> ERC5192<T> {
+    pub fn locked<S>(storage: &S, id: U256) -> Result<bool>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC721<T>>,
+    {
+        // ERC-5192 requires queries about nonexistent tokens to revert.
+        Borrow::<ERC721<T>>::borrow(storage).owner_of(id)?;
+
+        Ok(SoulboundStorage::load().locked.get(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        sign,
+        signer,
+        Host,
+        ResultExt,
+        ALICE,
+        BOB,
+    };
+    use crate::tokens::erc721::{
+        NotMinted,
+        Permit,
+    };
+    use crate::utils::eip712;
+    use alloc::string::String;
+    use stylus_sdk::abi::Bytes;
+
+    struct TestParams;
+
+    impl ERC721Params for TestParams {
+        const NAME: &'static str = "Test NFT";
+        const SYMBOL: &'static str = "TNFT";
+        const SOULBOUND: bool = true;
+
+        fn token_uri(_id: U256) -> String {
+            String::new()
+        }
+
+        fn before_token_transfer<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+            _storage: &mut S,
+            from: Address,
+            to: Address,
+            first_id: U256,
+            _batch_size: U256,
+        ) -> Result<(), Vec<u8>> {
+            Ok(Soulbound::before_token_transfer(from, to, first_id)?)
+        }
+
+        fn before_approve<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+            _storage: &mut S,
+            _owner: Address,
+            _spender: Address,
+            id: U256,
+        ) -> Result<(), Vec<u8>> {
+            Ok(Soulbound::before_approve(id)?)
+        }
+    }
+
+    sol_storage! {
+        pub struct TestNFT {
+            #[borrow]
+            ERC721<TestParams> erc721;
+            #[borrow]
+            ERC5192<TestParams> soulbound;
+        }
+    }
+
+    unsafe impl TopLevelStorage for TestNFT {}
+
+    type Soulbound = ERC5192<TestParams>;
+    type Token = ERC721<TestParams>;
+
+    const ID: U256 = U256::from_limbs([1, 0, 0, 0]);
+
+    fn setup() -> (Host, TestNFT) {
+        let host = Host::new();
+        let mut nft = host.contract::<TestNFT>();
+        Token::mint(&mut nft, ALICE, ID).unwrap_ok();
+        Soulbound::lock(&mut nft, ID).unwrap_ok();
+        host.set_sender(ALICE);
+        (host, nft)
+    }
+
+    #[test]
+    fn lock() {
+        let (host, mut nft) = setup();
+
+        assert!(Soulbound::locked(&nft, ID).unwrap_ok());
+        assert!(host.emitted(&Locked { tokenId: ID }));
+
+        Soulbound::unlock(&mut nft, ID).unwrap_ok();
+
+        assert!(!Soulbound::locked(&nft, ID).unwrap_ok());
+        assert!(host.emitted(&Unlocked { tokenId: ID }));
+    }
+
+    #[test]
+    fn locked_nonexistent() {
+        let (_host, mut nft) = setup();
+
+        let revert = Soulbound::locked(&nft, U256::from(2)).unwrap_revert();
+        assert_eq!(revert, NotMinted {}.encode());
+
+        let revert = Soulbound::lock(&mut nft, U256::from(2)).unwrap_revert();
+        assert_eq!(revert, NotMinted {}.encode());
+    }
+
+    #[test]
+    fn locked_token_cannot_move() {
+        let (_host, mut nft) = setup();
+        let locked = TokenLocked { tokenId: ID }.encode();
+
+        let revert = Token::transfer_from(&mut nft, ALICE, BOB, ID).unwrap_revert();
+        assert_eq!(revert, locked);
+
+        let revert = Token::safe_transfer_from(&mut nft, ALICE, BOB, ID).unwrap_revert();
+        assert_eq!(revert, locked);
+
+        let revert = Token::approve(&mut nft, BOB, ID).unwrap_revert();
+        assert_eq!(revert, locked);
+
+        assert_eq!(nft.erc721.owner_of(ID).unwrap_ok(), ALICE);
+    }
+
+    #[test]
+    fn locked_token_cannot_be_permitted() {
+        let (_host, mut nft) = setup();
+        let key = B256::repeat_byte(0x42);
+        let id = U256::from(2);
+        Token::mint(&mut nft, signer(key), id).unwrap_ok();
+        Soulbound::lock(&mut nft, id).unwrap_ok();
+
+        let struct_hash = eip712::hash_struct(&Permit {
+            spender: BOB,
+            tokenId: id,
+            nonce: U256::ZERO,
+            deadline: U256::MAX,
+        });
+        let domain_separator = nft.erc721.domain_separator().unwrap_ok();
+        let (v, r, s) = sign(key, eip712::hash_typed_data(domain_separator, struct_hash));
+        let sig = Bytes([&r[..], &s[..], &[v]].concat());
+
+        let revert = Token::permit(&mut nft, BOB, id, U256::MAX, sig).unwrap_revert();
+
+        assert_eq!(revert, TokenLocked { tokenId: id }.encode());
+        assert_eq!(nft.erc721.get_approved(id).unwrap_ok(), Address::ZERO);
+    }
+
+    #[test]
+    fn unlocked_token_moves() {
+        let (_host, mut nft) = setup();
+        Soulbound::unlock(&mut nft, ID).unwrap_ok();
+
+        Token::transfer_from(&mut nft, ALICE, BOB, ID).unwrap_ok();
+
+        assert_eq!(nft.erc721.owner_of(ID).unwrap_ok(), BOB);
+    }
+
+    #[test]
+    fn burn_locked() {
+        let (_host, mut nft) = setup();
+
+        Token::burn(&mut nft, ID).unwrap_ok();
+        Token::mint(&mut nft, BOB, ID).unwrap_ok();
+
+        assert!(!Soulbound::locked(&nft, ID).unwrap_ok());
+    }
+
+    #[test]
+    fn supports_interface() {
+        assert!(Token::supports_interface(0xb45a3c0eu32.to_be_bytes()).unwrap_ok());
+        assert!(Token::supports_interface(0x80ac58cdu32.to_be_bytes()).unwrap_ok());
+        assert!(!Token::supports_interface(0xad092b5cu32.to_be_bytes()).unwrap_ok());
+    }
+}
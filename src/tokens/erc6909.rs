@@ -4,24 +4,58 @@
 //! and is intended to be inherited by other contract types.
 //!
 //! You can configure the behavior of [`ERC6909`] via the [`ERC6909Params`] trait,
-//! which allows specifying the name, symbol, and token uri.
+//! which allows specifying the contract and token URIs served by [`ERC6909ContentURI`].
+//!
+//! [`ERC6909ContentURI`]: crate::tokens::erc6909_content_uri::ERC6909ContentURI
 //!
 //! Note that this code is unaudited and not fit for production use.
 
-use alloc::vec::Vec;
+use crate::host::msg;
+use alloc::{
+    string::String,
+    vec::Vec,
+};
 use alloy_primitives::{
     Address,
     U256,
 };
-use alloy_sol_types::sol;
+use alloy_sol_types::{
+    sol,
+    SolError,
+};
 use core::marker::PhantomData;
 use stylus_sdk::{
     evm,
-    msg,
     prelude::*,
 };
 
-pub trait ERC6909Params {}
+pub trait ERC6909Params {
+    /// Whether the inheriting contract also inherits [`ERC6909Metadata`], so that
+    /// `supportsInterface` reports it.
+    ///
+    /// [`ERC6909Metadata`]: crate::tokens::erc6909_metadata::ERC6909Metadata
+    const METADATA: bool = false;
+
+    /// Whether the inheriting contract also inherits [`ERC6909ContentURI`], so that
+    /// `supportsInterface` reports it.
+    ///
+    /// [`ERC6909ContentURI`]: crate::tokens::erc6909_content_uri::ERC6909ContentURI
+    const CONTENT_URI: bool = false;
+
+    /// The URI of the contract-level metadata, served by [`ERC6909ContentURI`].
+    ///
+    /// [`ERC6909ContentURI`]: crate::tokens::erc6909_content_uri::ERC6909ContentURI
+    fn contract_uri() -> String {
+        String::new()
+    }
+
+    /// The URI of the metadata of `id`, served by [`ERC6909ContentURI`].
+    ///
+    /// [`ERC6909ContentURI`]: crate::tokens::erc6909_content_uri::ERC6909ContentURI
+    fn token_uri(_id: U256) -> String {
+        String::new()
+    }
+}
 
 sol_storage! {
     /// ERC6909 implements all ERC-6909 methods
@@ -39,15 +73,24 @@ sol! {
     event OperatorSet(address indexed owner, address indexed operator, bool approved);
     event Approval(address indexed owner, address indexed spender, uint256 indexed id, uint256 amount);
     event Transfer(address caller, address indexed from, address indexed to, uint256 indexed id, uint256 amount);
+
+    error ERC6909InsufficientBalance(address sender, uint256 balance, uint256 needed, uint256 id);
+    error ERC6909InsufficientAllowance(address spender, uint256 allowance, uint256 needed, uint256 id);
 }
 
 /// Represents the ways methods may fail.
-pub enum ERC6909Error {}
+pub enum ERC6909Error {
+    ERC6909InsufficientBalance(ERC6909InsufficientBalance),
+    ERC6909InsufficientAllowance(ERC6909InsufficientAllowance),
+}
 
 /// We will soon provide a `#[derive(SolidityError)]` to clean this up.
 impl From<ERC6909Error> for Vec<u8> {
     fn from(val: ERC6909Error) -> Self {
-        match val {}
+        match val {
+            ERC6909Error::ERC6909InsufficientBalance(err) => err.encode(),
+            ERC6909Error::ERC6909InsufficientAllowance(err) => err.encode(),
+        }
     }
 }
 
@@ -73,10 +116,22 @@ impl<T: ERC6909Params> ERC6909<T> {
         });
     }
 
-    pub fn burn(&mut self, sender: Address, id: U256, amount: U256) {
+    pub fn burn(&mut self, sender: Address, id: U256, amount: U256) -> Result<()> {
         let mut from_balance = self.balance_of.setter(sender);
-        let balance = from_balance.get(id) - amount;
-        from_balance.insert(id, balance);
+        let balance = from_balance.get(id);
+
+        if balance < amount {
+            return Err(ERC6909Error::ERC6909InsufficientBalance(
+                ERC6909InsufficientBalance {
+                    sender,
+                    balance,
+                    needed: amount,
+                    id,
+                },
+            ));
+        }
+
+        from_balance.insert(id, balance - amount);
 
         let mut total_supply = self.total_supply.setter(id);
         let supply = total_supply.get() - amount;
@@ -89,15 +144,33 @@ impl<T: ERC6909Params> ERC6909<T> {
             id,
             amount,
         });
+
+        Ok(())
     }
 }
 
 #[external]
 impl<T: ERC6909Params> ERC6909<T> {
+    pub fn total_supply(&self, id: U256) -> Result<U256> {
+        Ok(self.total_supply.get(id))
+    }
+
     pub fn transfer(&mut self, receiver: Address, id: U256, amount: U256) -> Result<bool> {
         let mut from_balance = self.balance_of.setter(msg::sender());
-        let balance = from_balance.get(id) - amount;
-        from_balance.insert(id, balance);
+        let balance = from_balance.get(id);
+
+        if balance < amount {
+            return Err(ERC6909Error::ERC6909InsufficientBalance(
+                ERC6909InsufficientBalance {
+                    sender: msg::sender(),
+                    balance,
+                    needed: amount,
+                    id,
+                },
+            ));
+        }
+
+        from_balance.insert(id, balance - amount);
 
         let mut to_balance = self.balance_of.setter(receiver);
         let balance = to_balance.get(id) + amount;
@@ -124,6 +197,17 @@ impl<T: ERC6909Params> ERC6909<T> {
         if msg::sender() != sender && !self.is_operator.getter(sender).get(msg::sender()) {
             let allowed = self.allowance.getter(sender).getter(msg::sender()).get(id);
             if allowed != U256::MAX {
+                if allowed < amount {
+                    return Err(ERC6909Error::ERC6909InsufficientAllowance(
+                        ERC6909InsufficientAllowance {
+                            spender: msg::sender(),
+                            allowance: allowed,
+                            needed: amount,
+                            id,
+                        },
+                    ));
+                }
+
                 self.allowance
                     .setter(sender)
                     .setter(msg::sender())
@@ -132,8 +216,20 @@ impl<T: ERC6909Params> ERC6909<T> {
         }
 
         let mut from_balance = self.balance_of.setter(sender);
-        let balance = from_balance.get(id) - amount;
-        from_balance.insert(id, balance);
+        let balance = from_balance.get(id);
+
+        if balance < amount {
+            return Err(ERC6909Error::ERC6909InsufficientBalance(
+                ERC6909InsufficientBalance {
+                    sender,
+                    balance,
+                    needed: amount,
+                    id,
+                },
+            ));
+        }
+
+        from_balance.insert(id, balance - amount);
 
         let mut to_balance = self.balance_of.setter(receiver);
         let balance = to_balance.get(id) + amount;
@@ -182,7 +278,117 @@ impl<T: ERC6909Params> ERC6909<T> {
 
     pub fn supports_interface(interface: [u8; 4]) -> Result<bool> {
         let supported = interface == 0x01ffc9a7u32.to_be_bytes() // ERC165 Interface ID for ERC165
-            || interface == 0xb2e69f8au32.to_be_bytes(); // ERC165 Interface ID for ERC6909
+            || interface == 0xb2e69f8au32.to_be_bytes() // ERC165 Interface ID for ERC6909
+            || interface == 0xbd85b039u32.to_be_bytes() // ERC165 Interface ID for ERC6909TokenSupply
+            || (T::CONTENT_URI && interface == 0x20d88258u32.to_be_bytes()) // ERC165 Interface ID for ERC6909ContentURI
+            || (T::METADATA && interface == 0x71abc795u32.to_be_bytes()); // ERC165 Interface ID for ERC6909Metadata
         Ok(supported)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        Host,
+        ResultExt,
+        ALICE,
+        BOB,
+    };
+
+    struct TestParams;
+
+    impl ERC6909Params for TestParams {}
+
+    #[test]
+    fn transfer_insufficient_balance() {
+        let host = Host::new();
+        let mut token = host.contract::<ERC6909<TestParams>>();
+
+        token.mint(ALICE, U256::from(1), U256::from(900));
+
+        host.set_sender(ALICE);
+        let revert = token
+            .transfer(BOB, U256::from(1), U256::from(1_000))
+            .unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ERC6909InsufficientBalance {
+                sender: ALICE,
+                balance: U256::from(900),
+                needed: U256::from(1_000),
+                id: U256::from(1),
+            }
+            .encode()
+        );
+    }
+
+    #[test]
+    fn transfer_from_insufficient_allowance() {
+        let host = Host::new();
+        let mut token = host.contract::<ERC6909<TestParams>>();
+
+        token.mint(ALICE, U256::from(1), U256::from(1_000));
+
+        host.set_sender(ALICE);
+        token
+            .approve(BOB, U256::from(1), U256::from(900))
+            .unwrap_ok();
+
+        host.set_sender(BOB);
+        let revert = token
+            .transfer_from(ALICE, BOB, U256::from(1), U256::from(1_000))
+            .unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ERC6909InsufficientAllowance {
+                spender: BOB,
+                allowance: U256::from(900),
+                needed: U256::from(1_000),
+                id: U256::from(1),
+            }
+            .encode()
+        );
+    }
+
+    #[test]
+    fn total_supply() {
+        let host = Host::new();
+        let mut token = host.contract::<ERC6909<TestParams>>();
+
+        token.mint(ALICE, U256::from(1), U256::from(1_000));
+        token.mint(BOB, U256::from(1), U256::from(500));
+        token.burn(ALICE, U256::from(1), U256::from(300)).unwrap_ok();
+
+        assert_eq!(token.total_supply(U256::from(1)).unwrap_ok(), U256::from(1_200));
+        assert_eq!(token.total_supply(U256::from(2)).unwrap_ok(), U256::ZERO);
+
+        let interface = 0xbd85b039u32.to_be_bytes();
+        assert!(ERC6909::<TestParams>::supports_interface(interface).unwrap_ok());
+    }
+
+    #[test]
+    fn burn_insufficient_balance() {
+        let host = Host::new();
+        let mut token = host.contract::<ERC6909<TestParams>>();
+
+        token.mint(ALICE, U256::from(1), U256::from(900));
+
+        let revert = token
+            .burn(ALICE, U256::from(1), U256::from(1_000))
+            .unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ERC6909InsufficientBalance {
+                sender: ALICE,
+                balance: U256::from(900),
+                needed: U256::from(1_000),
+                id: U256::from(1),
+            }
+            .encode()
+        );
+    }
+}
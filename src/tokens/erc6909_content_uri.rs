@@ -0,0 +1,96 @@
+//! Provides an implementation of the ERC-6909 content URI extension.
+//!
+//! The [`ERC6909ContentURI`] type serves `contractURI` and `tokenURI` from
+//! [`ERC6909Params::contract_uri`] and [`ERC6909Params::token_uri`]. It is intended
+//! to be inherited alongside [`ERC6909`], whose `CONTENT_URI` parameter makes
+//! `supportsInterface` report it:
+//!
+//! ```ignore
+//! sol_storage! {
+//!     #[entrypoint]
+//!     pub struct MyToken {
+//!         #[borrow]
+//!         ERC6909<MyParams> erc6909;
+//!         #[borrow]
+//!         ERC6909ContentURI<MyParams> content_uri;
+//!     }
+//! }
+//!
+//! #[external]
+//! #[inherit(ERC6909<MyParams>, ERC6909ContentURI<MyParams>)]
+//! impl MyToken {}
+//! ```
+//!
+//! [`ERC6909`]: crate::tokens::erc6909::ERC6909
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use crate::tokens::erc6909::ERC6909Params;
+use alloc::{
+    string::String,
+    vec::Vec,
+};
+use alloy_primitives::U256;
+use core::marker::PhantomData;
+use stylus_sdk::prelude::*;
+
+sol_storage! {
+    /// ERC6909ContentURI serves the metadata URIs of an ERC6909 token
+    pub struct ERC6909ContentURI<T: ERC6909Params> {
+        PhantomData<T> phantom;
+    }
+}
+
+#[external]
+impl<T: ERC6909Params> ERC6909ContentURI<T> {
+    #[selector(name = "contractURI")]
+    pub fn contract_uri(&self) -> Result<String, Vec<u8>> {
+        Ok(T::contract_uri())
+    }
+
+    #[selector(name = "tokenURI")]
+    pub fn token_uri(&self, id: U256) -> Result<String, Vec<u8>> {
+        Ok(T::token_uri(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        Host,
+        ResultExt,
+    };
+    use crate::tokens::erc6909::ERC6909;
+    use alloc::format;
+
+    struct TestParams;
+
+    impl ERC6909Params for TestParams {
+        const CONTENT_URI: bool = true;
+
+        fn contract_uri() -> String {
+            "ipfs://contract.json".into()
+        }
+
+        fn token_uri(id: U256) -> String {
+            format!("ipfs://tokens/{id}.json")
+        }
+    }
+
+    #[test]
+    fn content_uri() {
+        let host = Host::new();
+        let content_uri = host.contract::<ERC6909ContentURI<TestParams>>();
+
+        assert_eq!(content_uri.contract_uri().unwrap_ok(), "ipfs://contract.json");
+        assert_eq!(content_uri.token_uri(U256::from(7)).unwrap_ok(), "ipfs://tokens/7.json");
+    }
+
+    #[test]
+    fn supports_interface() {
+        let interface = 0x20d88258u32.to_be_bytes();
+
+        assert!(ERC6909::<TestParams>::supports_interface(interface).unwrap_ok());
+    }
+}
@@ -0,0 +1,119 @@
+//! Provides an implementation of the ERC-6909 metadata extension.
+//!
+//! The [`ERC6909Metadata`] type stores a name, symbol and decimals for each id,
+//! so that wallets can display multi-token positions. It is intended to be
+//! inherited alongside [`ERC6909`], whose `METADATA` parameter makes
+//! `supportsInterface` report it:
+//!
+//! ```ignore
+//! sol_storage! {
+//!     #[entrypoint]
+//!     pub struct MyToken {
+//!         #[borrow]
+//!         ERC6909<MyParams> erc6909;
+//!         #[borrow]
+//!         ERC6909Metadata metadata;
+//!     }
+//! }
+//!
+//! #[external]
+//! #[inherit(ERC6909<MyParams>, ERC6909Metadata)]
+//! impl MyToken {}
+//! ```
+//!
+//! The metadata is only set through [`ERC6909Metadata::set_metadata`], so the
+//! inheriting contract decides who may change it.
+//!
+//! [`ERC6909`]: crate::tokens::erc6909::ERC6909
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use alloc::{
+    string::String,
+    vec::Vec,
+};
+use alloy_primitives::{
+    U256,
+    U8,
+};
+use stylus_sdk::prelude::*;
+
+sol_storage! {
+    /// ERC6909Metadata stores the metadata of each id of a token
+    pub struct ERC6909Metadata {
+        mapping(uint256 => TokenMetadata) metadata;
+    }
+
+    /// The name, symbol and decimals of an id
+    pub struct TokenMetadata {
+        string name;
+        string symbol;
+        uint8 decimals;
+    }
+}
+
+impl ERC6909Metadata {
+    /// Sets the name, symbol and decimals of `id`.
+    pub fn set_metadata(&mut self, id: U256, name: &str, symbol: &str, decimals: u8) {
+        let mut metadata = self.metadata.setter(id);
+        metadata.name.set_str(name);
+        metadata.symbol.set_str(symbol);
+        metadata.decimals.set(U8::from(decimals));
+    }
+}
+
+#[external]
+impl ERC6909Metadata {
+    pub fn name(&self, id: U256) -> Result<String, Vec<u8>> {
+        Ok(self.metadata.getter(id).name.get_string())
+    }
+
+    pub fn symbol(&self, id: U256) -> Result<String, Vec<u8>> {
+        Ok(self.metadata.getter(id).symbol.get_string())
+    }
+
+    pub fn decimals(&self, id: U256) -> Result<u8, Vec<u8>> {
+        Ok(self.metadata.getter(id).decimals.get().to::<u8>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        Host,
+        ResultExt,
+    };
+    use crate::tokens::erc6909::{
+        ERC6909Params,
+        ERC6909,
+    };
+
+    #[test]
+    fn metadata() {
+        let host = Host::new();
+        let mut metadata = host.contract::<ERC6909Metadata>();
+
+        metadata.set_metadata(U256::from(1), "Wrapped Ether", "WETH", 18);
+
+        assert_eq!(metadata.name(U256::from(1)).unwrap_ok(), "Wrapped Ether");
+        assert_eq!(metadata.symbol(U256::from(1)).unwrap_ok(), "WETH");
+        assert_eq!(metadata.decimals(U256::from(1)).unwrap_ok(), 18);
+
+        assert_eq!(metadata.name(U256::from(2)).unwrap_ok(), "");
+        assert_eq!(metadata.decimals(U256::from(2)).unwrap_ok(), 0);
+    }
+
+    struct MetadataParams;
+
+    impl ERC6909Params for MetadataParams {
+        const METADATA: bool = true;
+    }
+
+    #[test]
+    fn supports_interface() {
+        let interface = 0x71abc795u32.to_be_bytes();
+
+        assert!(ERC6909::<MetadataParams>::supports_interface(interface).unwrap_ok());
+    }
+}
@@ -4,16 +4,47 @@
 //! and is intended to be inherited by other contract types.
 //!
 //! You can configure the behavior of [`ERC721`] via the [`ERC721Params`] trait,
-//! which allows specifying the name, symbol, and token uri.
+//! which allows specifying the name, symbol, and token uri, or setting the
+//! name and symbol at runtime.
+//!
+//! Approvals can also be granted by signature through EIP-4494's `permit`.
+//!
+//! Extensions follow every mint, burn and transfer through the
+//! [`ERC721Params::before_token_transfer`] and [`ERC721Params::after_token_transfer`]
+//! hooks, and every approval through [`ERC721Params::before_approve`]. So that the
+//! hooks can reach the inheriting contract, the methods that move or approve
+//! tokens, `mint`, `safe_mint`, `mint_consecutive`, `burn`, `transfer_from` and
+//! `approve` included, take its storage rather than `&mut self`: call them as
+//! `ERC721::<MyParams>::mint(self, to, id)`.
+//!
+//! [`ERC721::mint_consecutive`] mints a batch of tokens with a single ownership
+//! record and an ERC-2309 `ConsecutiveTransfer` event. Batches take the ids from
+//! zero upward, one after the other, within the range reserved by
+//! [`ERC721Params::CONSECUTIVE_IDS`], and the owner of a token in a batch is found
+//! by scanning back to the start of its run, which is split as tokens change
+//! hands. Tokens minted on their own take the ids above that range, and are
+//! looked up directly.
 //!
 //! Note that this code is unaudited and not fit for production use.
 
+use crate::host::{
+    block,
+    msg,
+};
+use crate::utils::eip712::{
+    self,
+    Domain,
+    EIP712DomainChanged,
+    EIP712,
+};
+use crate::utils::signature_checker;
 use alloc::{
     string::String,
     vec::Vec,
 };
 use alloy_primitives::{
     Address,
+    B256,
     U256,
 };
 use alloy_sol_types::{
@@ -27,25 +58,127 @@ use core::{
 use stylus_sdk::{
     abi::Bytes,
     evm,
-    msg,
     prelude::*,
 };
 
-pub trait ERC721Params {
+/// The largest batch [`ERC721::mint_consecutive`] mints, which bounds how far
+/// an owner lookup scans back.
+pub const MAX_BATCH_SIZE: u64 = 5_000;
+
+pub trait ERC721Params: Sized {
     const NAME: &'static str;
 
     const SYMBOL: &'static str;
 
+    /// Whether the name and symbol are set once through `initialize`
+    /// instead of the constants above, so that one program can back many tokens.
+    const RUNTIME_METADATA: bool = false;
+
+    /// Whether the inheriting contract also inherits [`ERC2981`], so that
+    /// `supportsInterface` reports it.
+    ///
+    /// [`ERC2981`]: crate::tokens::royalty::ERC2981
+    const ROYALTIES: bool = false;
+
+    /// Whether the inheriting contract also inherits [`ERC721Enumerable`], so
+    /// that `supportsInterface` reports it.
+    ///
+    /// [`ERC721Enumerable`]: crate::tokens::erc721_enumerable::ERC721Enumerable
+    const ENUMERABLE: bool = false;
+
+    /// Whether the inheriting contract also inherits [`ERC4907`], so that
+    /// `supportsInterface` reports it.
+    ///
+    /// [`ERC4907`]: crate::tokens::erc4907::ERC4907
+    const RENTABLE: bool = false;
+
+    /// Whether the inheriting contract also inherits [`ERC5192`], so that
+    /// `supportsInterface` reports it.
+    ///
+    /// [`ERC5192`]: crate::tokens::erc5192::ERC5192
+    const SOULBOUND: bool = false;
+
+    /// Whether the inheriting contract emits ERC-4906 metadata updates, as
+    /// [`ERC721URIStorage`] does, so that `supportsInterface` reports them.
+    ///
+    /// [`ERC721URIStorage`]: crate::tokens::erc721_uri_storage::ERC721URIStorage
+    const METADATA_UPDATES: bool = false;
+
+    /// How many ids, from zero, are reserved for [`ERC721::mint_consecutive`].
+    /// Tokens minted on their own cannot take these ids, and batches cannot go
+    /// past them. The default of zero disables batches.
+    const CONSECUTIVE_IDS: u64 = 0;
+
     fn token_uri(id: U256) -> String;
+
+    /// Called before `batch_size` tokens starting at `first_id` move from `from` to
+    /// `to`, where `from` is zero for a mint and `to` is zero for a burn. Only
+    /// [`ERC721::mint_consecutive`] moves more than one token at a time. `storage`
+    /// is the contract inheriting [`ERC721`]. Returning `Err` reverts the transfer
+    /// with the given data.
+    fn before_token_transfer<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+        _storage: &mut S,
+        _from: Address,
+        _to: Address,
+        _first_id: U256,
+        _batch_size: U256,
+    ) -> Result<(), Vec<u8>> {
+        Ok(())
+    }
+
+    /// Called after the tokens have moved, with the same arguments as
+    /// [`ERC721Params::before_token_transfer`].
+    fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+        _storage: &mut S,
+        _from: Address,
+        _to: Address,
+        _first_id: U256,
+        _batch_size: U256,
+    ) -> Result<(), Vec<u8>> {
+        Ok(())
+    }
+
+    /// Called before `owner` approves `spender` for `id`, through `approve` or
+    /// `permit`. Returning `Err` reverts the approval with the given data.
+    fn before_approve<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+        _storage: &mut S,
+        _owner: Address,
+        _spender: Address,
+        _id: U256,
+    ) -> Result<(), Vec<u8>> {
+        Ok(())
+    }
+
+    /// Called before `initialize` sets the runtime metadata. `storage` is the
+    /// contract inheriting [`ERC721`]. Returning `Err` reverts the call with the
+    /// given data. The default lets anyone initialize the token, see
+    /// [`ERC721::initialize`].
+    fn before_initialize<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+        _storage: &mut S,
+    ) -> Result<(), Vec<u8>> {
+        Ok(())
+    }
 }
 
 sol_storage! {
     /// ERC721 implements all ERC-721 methods
+    ///
+    /// The runtime metadata, the EIP-4494 nonces, the cached EIP-712 domain and
+    /// the ERC-2309 batches follow the original four fields. ERC721 therefore takes
+    /// more slots than in earlier versions, which moves any field declared after
+    /// it: check the layout before upgrading a deployed contract.
     pub struct ERC721<T: ERC721Params> {
         mapping(uint256 => address) owner_of;
         mapping(address => uint256) balance_of;
         mapping(uint256 => address) get_approved;
         mapping(address => mapping(address => bool)) is_approved_for_all;
+        bool initialized;
+        string name;
+        string symbol;
+        mapping(uint256 => uint256) nonces;
+        EIP712 eip712;
+        uint256 next_batch_id;
+        mapping(uint256 => uint256) run_end;
         PhantomData<T> phantom;
     }
 }
@@ -55,6 +188,7 @@ sol! {
     event Transfer(address indexed from, address indexed to, uint256 indexed id);
     event Approval(address indexed owner, address indexed spender, uint256 indexed id);
     event ApprovalForAll(address indexed owner, address indexed operator, bool approved);
+    event ConsecutiveTransfer(uint256 indexed fromTokenId, uint256 toTokenId, address indexed fromAddress, address indexed toAddress);
 
     error NotMinted();
     error ZeroAddress();
@@ -63,6 +197,23 @@ sol! {
     error InvalidRecipient();
     error UnsafeRecipient();
     error AlreadyMinted();
+    error AlreadyInitialized();
+    error MetadataIsConstant();
+    error PermitDeadlineExpired();
+    error InvalidSigner();
+    error ERC721ExceededMaxBatchMint(uint256 batchSize, uint256 maxBatch);
+    error ERC721ForbiddenMint();
+    error ERC721ForbiddenBatchMint();
+}
+
+// Declare the EIP-712 message signed for a permit
+sol! {
+    struct Permit {
+        address spender;
+        uint256 tokenId;
+        uint256 nonce;
+        uint256 deadline;
+    }
 }
 
 /// Represents the ways methods may fail.
@@ -75,6 +226,14 @@ pub enum ERC721Error {
     UnsafeRecipient(UnsafeRecipient),
     CallFailed(stylus_sdk::call::Error),
     AlreadyMinted(AlreadyMinted),
+    AlreadyInitialized(AlreadyInitialized),
+    MetadataIsConstant(MetadataIsConstant),
+    PermitDeadlineExpired(PermitDeadlineExpired),
+    InvalidSigner(InvalidSigner),
+    ERC721ExceededMaxBatchMint(ERC721ExceededMaxBatchMint),
+    ERC721ForbiddenMint(ERC721ForbiddenMint),
+    ERC721ForbiddenBatchMint(ERC721ForbiddenBatchMint),
+    HookFailed(Vec<u8>),
 }
 
 impl From<stylus_sdk::call::Error> for ERC721Error {
@@ -95,6 +254,14 @@ impl From<ERC721Error> for Vec<u8> {
             ERC721Error::UnsafeRecipient(err) => err.encode(),
             ERC721Error::CallFailed(err) => err.into(),
             ERC721Error::AlreadyMinted(err) => err.encode(),
+            ERC721Error::AlreadyInitialized(err) => err.encode(),
+            ERC721Error::MetadataIsConstant(err) => err.encode(),
+            ERC721Error::PermitDeadlineExpired(err) => err.encode(),
+            ERC721Error::InvalidSigner(err) => err.encode(),
+            ERC721Error::ERC721ExceededMaxBatchMint(err) => err.encode(),
+            ERC721Error::ERC721ForbiddenMint(err) => err.encode(),
+            ERC721Error::ERC721ForbiddenBatchMint(err) => err.encode(),
+            ERC721Error::HookFailed(err) => err,
         }
     }
 }
@@ -103,7 +270,99 @@ impl From<ERC721Error> for Vec<u8> {
 type Result<T, E = ERC721Error> = core::result::Result<T, E>;
 
 impl<T: ERC721Params> ERC721<T> {
-    fn call_receiver<S: TopLevelStorage>(
+    /// Returns the EIP-712 domain of this token, named after the token.
+    pub fn domain(&self) -> Result<Domain> {
+        Ok(Domain::new(self.name()?, "1".into()))
+    }
+
+    fn before_token_transfer<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        first_id: U256,
+        batch_size: U256,
+    ) -> Result<()> {
+        T::before_token_transfer(storage, from, to, first_id, batch_size)
+            .map_err(ERC721Error::HookFailed)
+    }
+
+    fn after_token_transfer<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        first_id: U256,
+        batch_size: U256,
+    ) -> Result<()> {
+        T::after_token_transfer(storage, from, to, first_id, batch_size)
+            .map_err(ERC721Error::HookFailed)
+    }
+
+    fn before_approve<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        owner: Address,
+        spender: Address,
+        id: U256,
+    ) -> Result<()> {
+        T::before_approve(storage, owner, spender, id).map_err(ERC721Error::HookFailed)
+    }
+
+    /// Returns the owner of `id`, or zero if it is not minted.
+    pub fn owner(&self, id: U256) -> Address {
+        self.lookup(id).0
+    }
+
+    /// Returns the owner of `id` and the end of the run of batch-minted tokens
+    /// it starts or belongs to, which is zero for tokens minted on their own.
+    fn lookup(&self, id: U256) -> (Address, U256) {
+        let owner = self.owner_of.get(id);
+        let run_end = self.run_end.get(id);
+
+        // Batches only cover the ids below the next one, and explicit owners or
+        // burned batch tokens end the search.
+        if !owner.is_zero() || run_end != U256::ZERO || id >= self.next_batch_id.get() {
+            return (owner, run_end);
+        }
+
+        // Every id below the next batch belongs to a run starting at most one
+        // batch back.
+        let mut start = id;
+        for _ in 1..MAX_BATCH_SIZE {
+            if start == U256::ZERO {
+                break;
+            }
+            start -= U256::from(1);
+
+            let run_end = self.run_end.get(start);
+            if run_end != U256::ZERO {
+                return match id < run_end {
+                    true => (self.owner_of.get(start), run_end),
+                    false => (Address::ZERO, U256::ZERO),
+                };
+            }
+        }
+
+        (Address::ZERO, U256::ZERO)
+    }
+
+    /// Gives batch-minted `id` a slot of its own before it changes hands, and
+    /// hands the rest of its run to the token after it.
+    fn detach(&mut self, id: U256, owner: Address, run_end: U256) {
+        if run_end == U256::ZERO {
+            return;
+        }
+
+        let next = id + U256::from(1);
+        let next_unset = self.owner_of.get(next).is_zero() && self.run_end.get(next) == U256::ZERO;
+        if next < run_end && next_unset {
+            self.owner_of.insert(next, owner);
+            self.run_end.insert(next, run_end);
+        }
+
+        self.run_end.insert(id, next);
+    }
+
+    /// Checks that `to` accepts `id` if it is a contract, as safe transfers must.
+    pub fn call_receiver<S: TopLevelStorage>(
         storage: &mut S,
         id: U256,
         from: Address,
@@ -131,24 +390,35 @@ impl<T: ERC721Params> ERC721<T> {
         to: Address,
         data: Vec<u8>,
     ) -> Result<()> {
-        storage.borrow_mut().transfer_from(from, to, id)?;
+        Self::transfer_from(storage, from, to, id)?;
         Self::call_receiver(storage, id, from, to, data)
     }
 
-    pub fn mint(&mut self, to: Address, id: U256) -> Result<()> {
+    pub fn mint<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        to: Address,
+        id: U256,
+    ) -> Result<()> {
         if to.is_zero() {
             return Err(ERC721Error::InvalidRecipient(InvalidRecipient {}));
         }
 
-        if self.owner_of.get(id) != Address::ZERO {
+        if id < U256::from(T::CONSECUTIVE_IDS) {
+            return Err(ERC721Error::ERC721ForbiddenMint(ERC721ForbiddenMint {}));
+        }
+
+        if !storage.borrow_mut().owner(id).is_zero() {
             return Err(ERC721Error::AlreadyMinted(AlreadyMinted {}));
         }
 
-        let mut to_balance = self.balance_of.setter(to);
+        Self::before_token_transfer(storage, Address::ZERO, to, id, U256::from(1))?;
+
+        let token = storage.borrow_mut();
+        let mut to_balance = token.balance_of.setter(to);
         let balance = to_balance.get() + U256::from(1);
         to_balance.set(balance);
 
-        self.owner_of.setter(id).set(to);
+        token.owner_of.setter(id).set(to);
 
         evm::log(Transfer {
             from: Address::ZERO,
@@ -156,23 +426,88 @@ impl<T: ERC721Params> ERC721<T> {
             id,
         });
 
-        Ok(())
+        Self::after_token_transfer(storage, Address::ZERO, to, id, U256::from(1))
     }
 
-    pub fn burn(&mut self, id: U256) -> Result<()> {
-        let owner = self.owner_of.get(id);
+    /// Mints `quantity` tokens to `to` with the consecutive ids following the last
+    /// batch, returning the first one. Only the first token's owner is stored.
+    ///
+    /// Reverts with `ERC721ForbiddenBatchMint` if the batch would run past
+    /// [`ERC721Params::CONSECUTIVE_IDS`].
+    pub fn mint_consecutive<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        to: Address,
+        quantity: U256,
+    ) -> Result<U256> {
+        let first_id = storage.borrow_mut().next_batch_id.get();
+
+        if quantity == U256::ZERO {
+            return Ok(first_id);
+        }
+
+        if to.is_zero() {
+            return Err(ERC721Error::InvalidRecipient(InvalidRecipient {}));
+        }
+
+        let end = first_id.checked_add(quantity);
+        let end = match end {
+            Some(end) if quantity <= U256::from(MAX_BATCH_SIZE) => end,
+            _ => {
+                return Err(ERC721Error::ERC721ExceededMaxBatchMint(
+                    ERC721ExceededMaxBatchMint {
+                        batchSize: quantity,
+                        maxBatch: U256::from(MAX_BATCH_SIZE),
+                    },
+                ))
+            }
+        };
+
+        if end > U256::from(T::CONSECUTIVE_IDS) {
+            return Err(ERC721Error::ERC721ForbiddenBatchMint(ERC721ForbiddenBatchMint {}));
+        }
+
+        Self::before_token_transfer(storage, Address::ZERO, to, first_id, quantity)?;
+
+        let token = storage.borrow_mut();
+        let mut to_balance = token.balance_of.setter(to);
+        let balance = to_balance.get() + quantity;
+        to_balance.set(balance);
+
+        token.owner_of.insert(first_id, to);
+        token.run_end.insert(first_id, end);
+        token.next_batch_id.set(end);
+
+        evm::log(ConsecutiveTransfer {
+            fromTokenId: first_id,
+            toTokenId: end - U256::from(1),
+            fromAddress: Address::ZERO,
+            toAddress: to,
+        });
+
+        Self::after_token_transfer(storage, Address::ZERO, to, first_id, quantity)?;
+
+        Ok(first_id)
+    }
+
+    pub fn burn<S: TopLevelStorage + BorrowMut<Self>>(storage: &mut S, id: U256) -> Result<()> {
+        let (owner, run_end) = storage.borrow_mut().lookup(id);
 
         if owner.is_zero() {
             return Err(ERC721Error::NotMinted(NotMinted {}));
         }
 
-        let mut owner_balance = self.balance_of.setter(owner);
+        Self::before_token_transfer(storage, owner, Address::ZERO, id, U256::from(1))?;
+
+        let token = storage.borrow_mut();
+
+        let mut owner_balance = token.balance_of.setter(owner);
         let balance = owner_balance.get() - U256::from(1);
         owner_balance.set(balance);
 
-        self.owner_of.delete(id);
+        token.detach(id, owner, run_end);
+        token.owner_of.delete(id);
 
-        self.get_approved.delete(id);
+        token.get_approved.delete(id);
 
         evm::log(Transfer {
             from: owner,
@@ -180,30 +515,28 @@ impl<T: ERC721Params> ERC721<T> {
             id,
         });
 
-        Ok(())
+        Self::after_token_transfer(storage, owner, Address::ZERO, id, U256::from(1))
     }
 
-    pub fn safe_mint<S: TopLevelStorage>(
-        &mut self,
+    pub fn safe_mint<S: TopLevelStorage + BorrowMut<Self>>(
         storage: &mut S,
         to: Address,
         id: U256,
     ) -> Result<()> {
-        Self::mint(self, to, id)?;
+        Self::mint(storage, to, id)?;
 
         Self::call_receiver(storage, id, Address::ZERO, to, vec![])?;
 
         Ok(())
     }
 
-    pub fn safe_mint_with_data<S: TopLevelStorage>(
-        &mut self,
+    pub fn safe_mint_with_data<S: TopLevelStorage + BorrowMut<Self>>(
         storage: &mut S,
         to: Address,
         id: U256,
         data: Bytes,
     ) -> Result<()> {
-        Self::mint(self, to, id)?;
+        Self::mint(storage, to, id)?;
 
         Self::call_receiver(storage, id, Address::ZERO, to, data.0)?;
 
@@ -213,16 +546,59 @@ impl<T: ERC721Params> ERC721<T> {
 
 #[external]
 impl<T: ERC721Params> ERC721<T> {
-    pub fn name() -> Result<String> {
-        Ok(T::NAME.into())
+    /// Sets the metadata of a token using [`ERC721Params::RUNTIME_METADATA`].
+    /// It can only be called once, and never for tokens with constant metadata.
+    ///
+    /// # Security
+    ///
+    /// **Whoever calls `initialize` first decides the name and symbol.** A clone
+    /// or proxy deployed in one transaction and initialized in another can be
+    /// front-run, and by default any caller is accepted. Restrict the caller in
+    /// [`ERC721Params::before_initialize`], for instance to the factory, and have
+    /// the factory deploy and initialize the token in the same transaction.
+    pub fn initialize<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        name: String,
+        symbol: String,
+    ) -> Result<()> {
+        if !T::RUNTIME_METADATA {
+            return Err(ERC721Error::MetadataIsConstant(MetadataIsConstant {}));
+        }
+
+        if storage.borrow_mut().initialized.get() {
+            return Err(ERC721Error::AlreadyInitialized(AlreadyInitialized {}));
+        }
+
+        T::before_initialize(storage).map_err(ERC721Error::HookFailed)?;
+
+        let token = storage.borrow_mut();
+        token.initialized.set(true);
+        token.name.set_str(name);
+        token.symbol.set_str(symbol);
+
+        let domain = token.domain()?;
+        token.eip712.cache(&domain);
+        evm::log(EIP712DomainChanged {});
+
+        Ok(())
     }
 
-    pub fn symbol() -> Result<String> {
-        Ok(T::SYMBOL.into())
+    pub fn name(&self) -> Result<String> {
+        match T::RUNTIME_METADATA {
+            true => Ok(self.name.get_string()),
+            false => Ok(T::NAME.into()),
+        }
+    }
+
+    pub fn symbol(&self) -> Result<String> {
+        match T::RUNTIME_METADATA {
+            true => Ok(self.symbol.get_string()),
+            false => Ok(T::SYMBOL.into()),
+        }
     }
 
     pub fn owner_of(&self, id: U256) -> Result<Address> {
-        let owner = self.owner_of.get(id);
+        let owner = self.owner(id);
 
         if owner.is_zero() {
             return Err(ERC721Error::NotMinted(NotMinted {}));
@@ -252,14 +628,21 @@ impl<T: ERC721Params> ERC721<T> {
         Ok(T::token_uri(id))
     }
 
-    pub fn approve(&mut self, spender: Address, id: U256) -> Result<()> {
-        let owner = self.owner_of.get(id);
+    pub fn approve<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        spender: Address,
+        id: U256,
+    ) -> Result<()> {
+        let token = storage.borrow_mut();
+        let owner = token.owner(id);
 
-        if msg::sender() != owner || !self.is_approved_for_all.getter(owner).get(msg::sender()) {
+        if msg::sender() != owner && !token.is_approved_for_all.getter(owner).get(msg::sender()) {
             return Err(ERC721Error::NotAuthorized(NotAuthorized {}));
         }
 
-        self.get_approved.setter(id).set(spender);
+        Self::before_approve(storage, owner, spender, id)?;
+
+        storage.borrow_mut().get_approved.setter(id).set(spender);
 
         evm::log(Approval { owner, spender, id });
 
@@ -280,8 +663,16 @@ impl<T: ERC721Params> ERC721<T> {
         Ok(())
     }
 
-    pub fn transfer_from(&mut self, from: Address, to: Address, id: U256) -> Result<()> {
-        if from != self.owner_of.get(id) {
+    pub fn transfer_from<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        id: U256,
+    ) -> Result<()> {
+        let token = storage.borrow_mut();
+        let (owner, run_end) = token.lookup(id);
+
+        if from != owner {
             return Err(ERC721Error::WrongFrom(WrongFrom {}));
         }
 
@@ -290,27 +681,36 @@ impl<T: ERC721Params> ERC721<T> {
         }
 
         if msg::sender() != from
-            && !self.is_approved_for_all.getter(from).get(msg::sender())
-            && msg::sender() != self.get_approved.get(id)
+            && !token.is_approved_for_all.getter(from).get(msg::sender())
+            && msg::sender() != token.get_approved.get(id)
         {
             return Err(ERC721Error::NotAuthorized(NotAuthorized {}));
         }
 
-        let mut from_balance = self.balance_of.setter(from);
+        Self::before_token_transfer(storage, from, to, id, U256::from(1))?;
+
+        let token = storage.borrow_mut();
+        let mut from_balance = token.balance_of.setter(from);
         let balance = from_balance.get() - U256::from(1);
         from_balance.set(balance);
 
-        let mut to_balance = self.balance_of.setter(to);
+        let mut to_balance = token.balance_of.setter(to);
         let balance = to_balance.get() + U256::from(1);
         to_balance.set(balance);
 
-        self.owner_of.setter(id).set(to);
+        token.detach(id, owner, run_end);
+        token.owner_of.setter(id).set(to);
+
+        token.get_approved.delete(id);
 
-        self.get_approved.delete(id);
+        // Invalidates the permits signed by the previous owner.
+        let mut nonce = token.nonces.setter(id);
+        let next = nonce.get() + U256::from(1);
+        nonce.set(next);
 
         evm::log(Transfer { from, to, id });
 
-        Ok(())
+        Self::after_token_transfer(storage, from, to, id, U256::from(1))
     }
 
     pub fn safe_transfer_from<S: TopLevelStorage + BorrowMut<Self>>(
@@ -333,10 +733,75 @@ impl<T: ERC721Params> ERC721<T> {
         Self::safe_transfer(storage, id, from, to, data.0)
     }
 
+    /// Approves `spender` for `id` with a signature by its owner, as described
+    /// in EIP-4494. The owner may be a smart contract wallet using ERC-1271.
+    pub fn permit<S: TopLevelStorage + BorrowMut<Self>>(
+        storage: &mut S,
+        spender: Address,
+        id: U256,
+        deadline: U256,
+        sig: Bytes,
+    ) -> Result<()> {
+        if deadline < U256::from(block::timestamp()) {
+            return Err(ERC721Error::PermitDeadlineExpired(PermitDeadlineExpired {}));
+        }
+
+        let token = storage.borrow_mut();
+        let owner = token.owner_of(id)?;
+
+        let mut nonce_setter = token.nonces.setter(id);
+        let nonce = nonce_setter.get();
+        nonce_setter.set(nonce + U256::from(1));
+
+        let struct_hash = eip712::hash_struct(&Permit {
+            spender,
+            tokenId: id,
+            nonce,
+            deadline,
+        });
+
+        let digest = eip712::hash_typed_data(token.domain_separator()?, struct_hash);
+
+        if !signature_checker::is_valid_signature_now(&*storage, owner, digest, &sig) {
+            return Err(ERC721Error::InvalidSigner(InvalidSigner {}));
+        }
+
+        Self::before_approve(storage, owner, spender, id)?;
+
+        storage.borrow_mut().get_approved.setter(id).set(spender);
+
+        evm::log(Approval { owner, spender, id });
+
+        Ok(())
+    }
+
+    pub fn nonces(&self, id: U256) -> Result<U256> {
+        Ok(self.nonces.get(id))
+    }
+
+    #[selector(name = "DOMAIN_SEPARATOR")]
+    pub fn domain_separator(&self) -> Result<B256> {
+        let cached = match T::RUNTIME_METADATA {
+            true => self.eip712.cached_domain_separator(),
+            false => None,
+        };
+
+        match cached {
+            Some(separator) => Ok(separator),
+            None => Ok(self.domain()?.separator()),
+        }
+    }
+
     pub fn supports_interface(interface: [u8; 4]) -> Result<bool> {
         let supported = interface == 0x01ffc9a7u32.to_be_bytes() // ERC165 Interface ID for ERC165
             || interface == 0x80ac58cdu32.to_be_bytes() // ERC165 Interface ID for ERC721
-            || interface == 0x780e9d63u32.to_be_bytes(); // ERC165 Interface ID for ERC721Metadata
+            || interface == 0x5b5e139fu32.to_be_bytes() // ERC165 Interface ID for ERC721Metadata
+            || interface == 0x5604e225u32.to_be_bytes() // ERC165 Interface ID for EIP-4494
+            || (T::ROYALTIES && interface == 0x2a55205au32.to_be_bytes()) // ERC165 Interface ID for ERC2981
+            || (T::ENUMERABLE && interface == 0x780e9d63u32.to_be_bytes()) // ERC165 Interface ID for ERC721Enumerable
+            || (T::RENTABLE && interface == 0xad092b5cu32.to_be_bytes()) // ERC165 Interface ID for ERC4907
+            || (T::SOULBOUND && interface == 0xb45a3c0eu32.to_be_bytes()) // ERC165 Interface ID for ERC5192
+            || (T::METADATA_UPDATES && interface == 0x49064906u32.to_be_bytes()); // ERC165 Interface ID for ERC4906
         Ok(supported)
     }
 }
@@ -346,3 +811,353 @@ sol_interface! {
         function onERC721Received(address operator, address from, uint256 id, bytes data) external returns(bytes4);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        sign,
+        signer,
+        Host,
+        ResultExt,
+        ALICE,
+        BOB,
+    };
+    use alloy_primitives::{
+        address,
+        b256,
+        keccak256,
+    };
+    use alloy_sol_types::SolType;
+
+    struct TestParams;
+
+    impl ERC721Params for TestParams {
+        const NAME: &'static str = "Test NFT";
+        const SYMBOL: &'static str = "TNFT";
+        const CONSECUTIVE_IDS: u64 = 100;
+
+        fn token_uri(_id: U256) -> String {
+            String::new()
+        }
+    }
+
+    sol_storage! {
+        pub struct TestNFT {
+            #[borrow]
+            ERC721<TestParams> erc721;
+        }
+    }
+
+    unsafe impl TopLevelStorage for TestNFT {}
+
+    type Token = ERC721<TestParams>;
+
+    const TOKEN: Address = address!("000000000000000000000000000000000000c0de");
+    const OWNER_KEY: B256 = B256::repeat_byte(0x42);
+    const ID: U256 = U256::from_limbs([1_000, 0, 0, 0]);
+
+    // keccak256("Permit(address spender,uint256 tokenId,uint256 nonce,uint256 deadline)")
+    const PERMIT_TYPEHASH: B256 =
+        b256!("49ecf333e5b8c95c40fdafc95c1ad136e8914a8fb55e9dc8bb01eaa83a2df9ad");
+
+    fn setup() -> (Host, TestNFT) {
+        let host = Host::new();
+        host.set_chain_id(1);
+        host.set_contract_address(TOKEN);
+        let mut nft = host.contract::<TestNFT>();
+        Token::mint(&mut nft, signer(OWNER_KEY), ID).unwrap_ok();
+        (host, nft)
+    }
+
+    fn permit_signature(nft: &TestNFT, key: B256, spender: Address, nonce: U256) -> Bytes {
+        let struct_hash = keccak256(<sol! { (bytes32, address, uint256, uint256, uint256) }>::encode(
+            &(*PERMIT_TYPEHASH, spender, ID, nonce, U256::MAX),
+        ));
+        let domain_separator = nft.erc721.domain_separator().unwrap_ok();
+        let digest = keccak256([&[0x19, 0x01], &domain_separator[..], &struct_hash[..]].concat());
+
+        let (v, r, s) = sign(key, digest);
+        Bytes([&r[..], &s[..], &[v]].concat())
+    }
+
+    #[test]
+    fn permit_typehash() {
+        assert_eq!(
+            keccak256("Permit(address spender,uint256 tokenId,uint256 nonce,uint256 deadline)"),
+            PERMIT_TYPEHASH
+        );
+    }
+
+    #[test]
+    fn permit() {
+        let (host, mut nft) = setup();
+        let sig = permit_signature(&nft, OWNER_KEY, BOB, U256::ZERO);
+
+        Token::permit(&mut nft, BOB, ID, U256::MAX, sig).unwrap_ok();
+
+        assert_eq!(nft.erc721.get_approved(ID).unwrap_ok(), BOB);
+        assert_eq!(nft.erc721.nonces(ID).unwrap_ok(), U256::from(1));
+        assert!(host.emitted(&Approval {
+            owner: signer(OWNER_KEY),
+            spender: BOB,
+            id: ID,
+        }));
+    }
+
+    #[test]
+    fn permit_replay() {
+        let (_host, mut nft) = setup();
+        let sig = permit_signature(&nft, OWNER_KEY, BOB, U256::ZERO);
+
+        Token::permit(&mut nft, BOB, ID, U256::MAX, sig.clone()).unwrap_ok();
+        let revert = Token::permit(&mut nft, BOB, ID, U256::MAX, sig).unwrap_revert();
+
+        assert_eq!(revert, InvalidSigner {}.encode());
+    }
+
+    #[test]
+    fn permit_after_transfer() {
+        let (host, mut nft) = setup();
+        let sig = permit_signature(&nft, OWNER_KEY, BOB, U256::ZERO);
+
+        // The previous owner's signature is void once the token moves on.
+        host.set_sender(signer(OWNER_KEY));
+        Token::transfer_from(&mut nft, signer(OWNER_KEY), BOB, ID).unwrap_ok();
+        assert_eq!(nft.erc721.nonces(ID).unwrap_ok(), U256::from(1));
+
+        let revert = Token::permit(&mut nft, BOB, ID, U256::MAX, sig).unwrap_revert();
+
+        assert_eq!(revert, InvalidSigner {}.encode());
+    }
+
+    #[test]
+    fn permit_wrong_signer() {
+        let (_host, mut nft) = setup();
+        let sig = permit_signature(&nft, B256::repeat_byte(0x43), BOB, U256::ZERO);
+
+        let revert = Token::permit(&mut nft, BOB, ID, U256::MAX, sig).unwrap_revert();
+
+        assert_eq!(revert, InvalidSigner {}.encode());
+    }
+
+    #[test]
+    fn permit_past_deadline() {
+        let (host, mut nft) = setup();
+        host.set_timestamp(1_000);
+
+        let revert =
+            Token::permit(&mut nft, BOB, ID, U256::from(999), Bytes(vec![])).unwrap_revert();
+
+        assert_eq!(revert, PermitDeadlineExpired {}.encode());
+    }
+
+    #[test]
+    fn approve() {
+        let (host, mut nft) = setup();
+
+        host.set_sender(BOB);
+        let revert = Token::approve(&mut nft, BOB, ID).unwrap_revert();
+        assert_eq!(revert, NotAuthorized {}.encode());
+
+        host.set_sender(signer(OWNER_KEY));
+        Token::approve(&mut nft, BOB, ID).unwrap_ok();
+        assert_eq!(nft.erc721.get_approved(ID).unwrap_ok(), BOB);
+
+        nft.erc721.set_approval_for_all(BOB, true).unwrap_ok();
+        host.set_sender(BOB);
+        Token::approve(&mut nft, TOKEN, ID).unwrap_ok();
+        assert_eq!(nft.erc721.get_approved(ID).unwrap_ok(), TOKEN);
+    }
+
+    #[test]
+    fn supports_interface() {
+        assert!(Token::supports_interface(0x01ffc9a7u32.to_be_bytes()).unwrap_ok());
+        assert!(Token::supports_interface(0x80ac58cdu32.to_be_bytes()).unwrap_ok());
+        assert!(Token::supports_interface(0x5b5e139fu32.to_be_bytes()).unwrap_ok());
+        assert!(Token::supports_interface(0x5604e225u32.to_be_bytes()).unwrap_ok());
+        assert!(!Token::supports_interface(0x780e9d63u32.to_be_bytes()).unwrap_ok());
+    }
+
+    #[test]
+    fn initialize_constant_metadata() {
+        let (_host, mut nft) = setup();
+
+        let revert = Token::initialize(&mut nft, "Other".into(), "OTHER".into()).unwrap_revert();
+
+        assert_eq!(revert, MetadataIsConstant {}.encode());
+    }
+
+    struct RuntimeParams;
+
+    sol! {
+        error NotFactory(address caller);
+    }
+
+    impl ERC721Params for RuntimeParams {
+        const NAME: &'static str = "";
+        const SYMBOL: &'static str = "";
+        const RUNTIME_METADATA: bool = true;
+
+        fn token_uri(_id: U256) -> String {
+            String::new()
+        }
+
+        fn before_initialize<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+            _storage: &mut S,
+        ) -> Result<(), Vec<u8>> {
+            match msg::sender() {
+                FACTORY => Ok(()),
+                caller => Err(NotFactory { caller }.encode()),
+            }
+        }
+    }
+
+    sol_storage! {
+        pub struct RuntimeNFT {
+            #[borrow]
+            ERC721<RuntimeParams> erc721;
+        }
+    }
+
+    unsafe impl TopLevelStorage for RuntimeNFT {}
+
+    const FACTORY: Address = address!("000000000000000000000000000000000000fac7");
+
+    #[test]
+    fn initialize_gated_by_hook() {
+        let host = Host::new();
+        let mut nft = host.contract::<RuntimeNFT>();
+        host.set_sender(ALICE);
+
+        let revert = ERC721::<RuntimeParams>::initialize(&mut nft, "Other".into(), "OTHER".into())
+            .unwrap_revert();
+        assert_eq!(revert, NotFactory { caller: ALICE }.encode());
+
+        host.set_sender(FACTORY);
+        ERC721::<RuntimeParams>::initialize(&mut nft, "Test NFT".into(), "TNFT".into()).unwrap_ok();
+
+        assert_eq!(nft.erc721.name().unwrap_ok(), "Test NFT");
+        assert_eq!(nft.erc721.symbol().unwrap_ok(), "TNFT");
+    }
+
+    /// Mints ids 0 to 9 to `ALICE` in a batch.
+    fn batch() -> (Host, TestNFT) {
+        let host = Host::new();
+        let mut nft = host.contract::<TestNFT>();
+        let first = Token::mint_consecutive(&mut nft, ALICE, U256::from(10)).unwrap_ok();
+        assert_eq!(first, U256::ZERO);
+        (host, nft)
+    }
+
+    #[test]
+    fn mint_consecutive() {
+        let (host, nft) = batch();
+
+        assert_eq!(nft.erc721.balance_of(ALICE).unwrap_ok(), U256::from(10));
+        for id in 0..10 {
+            assert_eq!(nft.erc721.owner_of(U256::from(id)).unwrap_ok(), ALICE);
+        }
+        assert_eq!(
+            nft.erc721.owner_of(U256::from(10)).unwrap_revert(),
+            NotMinted {}.encode()
+        );
+        assert!(host.emitted(&ConsecutiveTransfer {
+            fromTokenId: U256::ZERO,
+            toTokenId: U256::from(9),
+            fromAddress: Address::ZERO,
+            toAddress: ALICE,
+        }));
+    }
+
+    #[test]
+    fn transfer_from_batch() {
+        let (host, mut nft) = batch();
+        host.set_sender(ALICE);
+
+        Token::transfer_from(&mut nft, ALICE, BOB, U256::from(5)).unwrap_ok();
+        Token::transfer_from(&mut nft, ALICE, BOB, U256::from(6)).unwrap_ok();
+        Token::transfer_from(&mut nft, ALICE, BOB, U256::ZERO).unwrap_ok();
+
+        for id in 0..10 {
+            let owner = if [0, 5, 6].contains(&id) { BOB } else { ALICE };
+            assert_eq!(nft.erc721.owner_of(U256::from(id)).unwrap_ok(), owner);
+        }
+        assert_eq!(nft.erc721.balance_of(ALICE).unwrap_ok(), U256::from(7));
+        assert_eq!(nft.erc721.balance_of(BOB).unwrap_ok(), U256::from(3));
+    }
+
+    #[test]
+    fn burn_batch() {
+        let (_host, mut nft) = batch();
+
+        Token::burn(&mut nft, U256::from(9)).unwrap_ok();
+        Token::burn(&mut nft, U256::from(2)).unwrap_ok();
+
+        for id in 0..10 {
+            let owner = nft.erc721.owner_of(U256::from(id));
+            match id {
+                2 | 9 => assert_eq!(owner.unwrap_revert(), NotMinted {}.encode()),
+                _ => assert_eq!(owner.unwrap_ok(), ALICE),
+            }
+        }
+        assert_eq!(nft.erc721.balance_of(ALICE).unwrap_ok(), U256::from(8));
+
+        // Burned batch tokens stay burned, since their ids remain reserved.
+        let revert = Token::mint(&mut nft, BOB, U256::from(2)).unwrap_revert();
+        assert_eq!(revert, ERC721ForbiddenMint {}.encode());
+    }
+
+    #[test]
+    fn mint_after_batch() {
+        let (_host, mut nft) = batch();
+
+        let revert = Token::mint(&mut nft, BOB, U256::from(7)).unwrap_revert();
+        assert_eq!(revert, ERC721ForbiddenMint {}.encode());
+        let revert = Token::mint(&mut nft, BOB, U256::from(99)).unwrap_revert();
+        assert_eq!(revert, ERC721ForbiddenMint {}.encode());
+
+        // Tokens minted on their own leave the next batch where it was.
+        Token::mint(&mut nft, BOB, U256::from(100)).unwrap_ok();
+        Token::mint(&mut nft, BOB, U256::from(500_000)).unwrap_ok();
+        let first = Token::mint_consecutive(&mut nft, BOB, U256::from(2)).unwrap_ok();
+
+        assert_eq!(first, U256::from(10));
+        assert_eq!(nft.erc721.owner_of(U256::from(11)).unwrap_ok(), BOB);
+        assert_eq!(
+            nft.erc721.owner_of(U256::from(12)).unwrap_revert(),
+            NotMinted {}.encode()
+        );
+        assert_eq!(nft.erc721.balance_of(BOB).unwrap_ok(), U256::from(4));
+    }
+
+    #[test]
+    fn mint_consecutive_past_reserved_ids() {
+        let (_host, mut nft) = batch();
+
+        let revert = Token::mint_consecutive(&mut nft, ALICE, U256::from(91)).unwrap_revert();
+        assert_eq!(revert, ERC721ForbiddenBatchMint {}.encode());
+
+        let first = Token::mint_consecutive(&mut nft, BOB, U256::from(90)).unwrap_ok();
+        assert_eq!(first, U256::from(10));
+        assert_eq!(nft.erc721.owner_of(U256::from(99)).unwrap_ok(), BOB);
+        assert_eq!(nft.erc721.balance_of(ALICE).unwrap_ok(), U256::from(10));
+    }
+
+    #[test]
+    fn mint_consecutive_too_many() {
+        let (_host, mut nft) = setup();
+
+        let revert = Token::mint_consecutive(&mut nft, ALICE, U256::from(MAX_BATCH_SIZE + 1))
+            .unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ERC721ExceededMaxBatchMint {
+                batchSize: U256::from(MAX_BATCH_SIZE + 1),
+                maxBatch: U256::from(MAX_BATCH_SIZE),
+            }
+            .encode()
+        );
+    }
+}
@@ -0,0 +1,421 @@
+//! Provides an enumeration extension for the [`ERC721`] token.
+//!
+//! The [`ERC721Enumerable`] type indexes every token and the tokens of each
+//! owner, so that `totalSupply`, `tokenByIndex` and `tokenOfOwnerByIndex` can
+//! list them without an off-chain indexer.
+//!
+//! The indices are updated by [`ERC721Enumerable::after_token_transfer`], which
+//! the token's params call from [`ERC721Params::after_token_transfer`] so that
+//! mints, burns and transfers of every kind are indexed. [`ERC721Enumerable`] is
+//! inherited alongside [`ERC721`], whose `supportsInterface` reports it once the
+//! params set [`ERC721Params::ENUMERABLE`]:
+//!
+//! ```ignore
+//! impl ERC721Params for MyParams {
+//!     // ...
+//!
+//!     const ENUMERABLE: bool = true;
+//!
+//!     fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+//!         storage: &mut S,
+//!         from: Address,
+//!         to: Address,
+//!         first_id: U256,
+//!         batch_size: U256,
+//!     ) -> Result<(), Vec<u8>> {
+//!         ERC721Enumerable::<Self>::after_token_transfer(
+//!             storage,
+//!             from,
+//!             to,
+//!             first_id,
+//!             batch_size,
+//!         );
+//!         Ok(())
+//!     }
+//! }
+//!
+//! sol_storage! {
+//!     #[entrypoint]
+//!     pub struct MyNFT {
+//!         #[borrow]
+//!         ERC721<MyParams> erc721;
+//!         #[borrow]
+//!         ERC721Enumerable<MyParams> enumerable;
+//!     }
+//! }
+//!
+//! #[external]
+//! #[inherit(ERC721Enumerable<MyParams>, ERC721<MyParams>)]
+//! impl MyNFT {}
+//! ```
+//!
+//! The indices live in the `rustmate.ERC721Enumerable` namespace, see
+//! [`crate::utils::namespace`]. Tokens minted with
+//! [`ERC721::mint_consecutive`] are indexed one by one, which gives up most of
+//! the savings of a batch.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use crate::tokens::erc721::{
+    ERC721Error,
+    ERC721Params,
+    ERC721,
+};
+use crate::utils::namespace;
+use alloc::vec::Vec;
+use alloy_primitives::{
+    b256,
+    Address,
+    B256,
+    U256,
+};
+use alloy_sol_types::{
+    sol,
+    SolError,
+};
+use core::{
+    borrow::{
+        Borrow,
+        BorrowMut,
+    },
+    marker::PhantomData,
+};
+use stylus_sdk::prelude::*;
+
+sol_storage! {
+    /// ERC721Enumerable indexes the tokens of an [`ERC721`]
+    pub struct ERC721Enumerable<T: ERC721Params> {
+        PhantomData<T> phantom;
+    }
+
+    /// The indices, kept in the `rustmate.ERC721Enumerable` namespace
+    pub struct EnumerableStorage {
+        mapping(address => mapping(uint256 => uint256)) owned_tokens;
+        mapping(uint256 => uint256) owned_tokens_index;
+        uint256[] all_tokens;
+        mapping(uint256 => uint256) all_tokens_index;
+    }
+}
+
+/// keccak256(abi.encode(uint256(keccak256("rustmate.ERC721Enumerable")) - 1)) & ~bytes32(uint256(0xff))
+const STORAGE_SLOT: B256 =
+    b256!("12595df85256877a33d5f0495790e49ba437e11b3e3876c75e17e2808a433700");
+
+// Declare Solidity error types
+sol! {
+    error ERC721OutOfBoundsIndex(address owner, uint256 index);
+}
+
+/// Represents the ways methods may fail.
+pub enum ERC721EnumerableError {
+    ERC721OutOfBoundsIndex(ERC721OutOfBoundsIndex),
+    ERC721(ERC721Error),
+}
+
+impl From<ERC721Error> for ERC721EnumerableError {
+    fn from(err: ERC721Error) -> Self {
+        Self::ERC721(err)
+    }
+}
+
+/// We will soon provide a `#[derive(SolidityError)]` to clean this up.
+impl From<ERC721EnumerableError> for Vec<u8> {
+    fn from(val: ERC721EnumerableError) -> Self {
+        match val {
+            ERC721EnumerableError::ERC721OutOfBoundsIndex(err) => err.encode(),
+            ERC721EnumerableError::ERC721(err) => err.into(),
+        }
+    }
+}
+
+/// Simplifies the result type for the contract's methods.
+type Result<T, E = ERC721EnumerableError> = core::result::Result<T, E>;
+
+impl EnumerableStorage {
+    /// Returns the indices of the token in their namespace.
+    fn load() -> Self {
+        namespace::load(STORAGE_SLOT)
+    }
+
+    fn add_token_to_owner(&mut self, to: Address, id: U256, index: U256) {
+        self.owned_tokens.setter(to).insert(index, id);
+        self.owned_tokens_index.insert(id, index);
+    }
+
+    /// Removes `id` from the list of `from`, filling its slot with the token at
+    /// `last_index`.
+    fn remove_token_from_owner(&mut self, from: Address, id: U256, last_index: U256) {
+        let index = self.owned_tokens_index.get(id);
+        let mut owned_tokens = self.owned_tokens.setter(from);
+
+        if index != last_index {
+            let last_id = owned_tokens.get(last_index);
+            owned_tokens.insert(index, last_id);
+            self.owned_tokens_index.insert(last_id, index);
+        }
+
+        owned_tokens.delete(last_index);
+        self.owned_tokens_index.delete(id);
+    }
+
+    fn add_token_to_all(&mut self, id: U256) {
+        self.all_tokens_index
+            .insert(id, U256::from(self.all_tokens.len()));
+        self.all_tokens.push(id);
+    }
+
+    /// Removes `id` from the list of all tokens, moving the last token into its slot.
+    fn remove_token_from_all(&mut self, id: U256) {
+        let index = self.all_tokens_index.get(id);
+        let last_id = self.all_tokens.pop().unwrap();
+
+        if last_id != id {
+            self.all_tokens.setter(index).unwrap().set(last_id);
+            self.all_tokens_index.insert(last_id, index);
+        }
+
+        self.all_tokens_index.delete(id);
+    }
+}
+
+impl<T: ERC721Params> ERC721Enumerable<T> {
+    /// Moves the tokens from the list of `from` to that of `to`, once the [`ERC721`]
+    /// balances have been updated. Call it from [`ERC721Params::after_token_transfer`].
+    pub fn after_token_transfer<S>(
+        storage: &mut S,
+        from: Address,
+        to: Address,
+        first_id: U256,
+        batch_size: U256,
+    ) where
+        S: TopLevelStorage + BorrowMut<ERC721<T>>,
+    {
+        if from == to {
+            return;
+        }
+
+        let token: &mut ERC721<T> = storage.borrow_mut();
+        let from_balance = token.balance_of.get(from);
+        let to_balance = token.balance_of.get(to);
+
+        let mut indices = EnumerableStorage::load();
+        let mut offset = U256::ZERO;
+        while offset < batch_size {
+            let id = first_id + offset;
+
+            if from.is_zero() {
+                indices.add_token_to_all(id);
+            } else {
+                indices.remove_token_from_owner(from, id, from_balance);
+            }
+
+            if to.is_zero() {
+                indices.remove_token_from_all(id);
+            } else {
+                indices.add_token_to_owner(to, id, to_balance - batch_size + offset);
+            }
+
+            offset += U256::from(1);
+        }
+    }
+}
+
+#[external]
+#[inherit(ERC721<T>)]
+impl<T: ERC721Params> ERC721Enumerable<T> {
+    pub fn total_supply(&self) -> Result<U256> {
+        Ok(U256::from(EnumerableStorage::load().all_tokens.len()))
+    }
+
+    pub fn token_by_index(&self, index: U256) -> Result<U256> {
+        EnumerableStorage::load().all_tokens.get(index).ok_or(
+            ERC721EnumerableError::ERC721OutOfBoundsIndex(ERC721OutOfBoundsIndex {
+                owner: Address::ZERO,
+                index,
+            }),
+        )
+    }
+
+    pub fn token_of_owner_by_index<S>(storage: &S, owner: Address, index: U256) -> Result<U256>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC721<T>>,
+    {
+        if index >= Borrow::<ERC721<T>>::borrow(storage).balance_of(owner)? {
+            return Err(ERC721EnumerableError::ERC721OutOfBoundsIndex(
+                ERC721OutOfBoundsIndex { owner, index },
+            ));
+        }
+
+        Ok(EnumerableStorage::load().owned_tokens.getter(owner).get(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        Host,
+        ResultExt,
+        ALICE,
+        BOB,
+    };
+    use alloc::string::String;
+
+    struct TestParams;
+
+    impl ERC721Params for TestParams {
+        const NAME: &'static str = "Test NFT";
+        const SYMBOL: &'static str = "TNFT";
+        const ENUMERABLE: bool = true;
+        const CONSECUTIVE_IDS: u64 = 3;
+
+        fn token_uri(_id: U256) -> String {
+            String::new()
+        }
+
+        fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+            storage: &mut S,
+            from: Address,
+            to: Address,
+            first_id: U256,
+            batch_size: U256,
+        ) -> Result<(), Vec<u8>> {
+            Enumerable::after_token_transfer(storage, from, to, first_id, batch_size);
+            Ok(())
+        }
+    }
+
+    sol_storage! {
+        pub struct TestNFT {
+            #[borrow]
+            ERC721<TestParams> erc721;
+            #[borrow]
+            ERC721Enumerable<TestParams> enumerable;
+        }
+    }
+
+    unsafe impl TopLevelStorage for TestNFT {}
+
+    type Enumerable = ERC721Enumerable<TestParams>;
+    type Token = ERC721<TestParams>;
+
+    fn tokens_of(nft: &TestNFT, owner: Address) -> Vec<U256> {
+        let balance = nft.erc721.balance_of(owner).unwrap_ok();
+        (0..balance.to::<u64>())
+            .map(|index| {
+                Enumerable::token_of_owner_by_index(nft, owner, U256::from(index)).unwrap_ok()
+            })
+            .collect()
+    }
+
+    fn all_tokens(nft: &TestNFT) -> Vec<U256> {
+        let total_supply = nft.enumerable.total_supply().unwrap_ok();
+        (0..total_supply.to::<u64>())
+            .map(|index| nft.enumerable.token_by_index(U256::from(index)).unwrap_ok())
+            .collect()
+    }
+
+    fn setup() -> (Host, TestNFT) {
+        let host = Host::new();
+        let mut nft = host.contract::<TestNFT>();
+        for id in 3..=5 {
+            Token::mint(&mut nft, ALICE, U256::from(id)).unwrap_ok();
+        }
+        (host, nft)
+    }
+
+    #[test]
+    fn mint() {
+        let (_host, nft) = setup();
+
+        assert_eq!(nft.enumerable.total_supply().unwrap_ok(), U256::from(3));
+        assert_eq!(all_tokens(&nft), [U256::from(3), U256::from(4), U256::from(5)]);
+        assert_eq!(tokens_of(&nft, ALICE), [U256::from(3), U256::from(4), U256::from(5)]);
+    }
+
+    #[test]
+    fn transfer() {
+        let (host, mut nft) = setup();
+
+        host.set_sender(ALICE);
+        Token::transfer_from(&mut nft, ALICE, BOB, U256::from(3)).unwrap_ok();
+
+        assert_eq!(tokens_of(&nft, ALICE), [U256::from(5), U256::from(4)]);
+        assert_eq!(tokens_of(&nft, BOB), [U256::from(3)]);
+        assert_eq!(all_tokens(&nft), [U256::from(3), U256::from(4), U256::from(5)]);
+    }
+
+    #[test]
+    fn burn() {
+        let (_host, mut nft) = setup();
+
+        Token::burn(&mut nft, U256::from(4)).unwrap_ok();
+
+        assert_eq!(nft.enumerable.total_supply().unwrap_ok(), U256::from(2));
+        assert_eq!(all_tokens(&nft), [U256::from(3), U256::from(5)]);
+        assert_eq!(tokens_of(&nft, ALICE), [U256::from(3), U256::from(5)]);
+    }
+
+    #[test]
+    fn safe_transfer() {
+        let (host, mut nft) = setup();
+
+        host.set_sender(ALICE);
+        Token::safe_transfer_from(&mut nft, ALICE, BOB, U256::from(5)).unwrap_ok();
+
+        assert_eq!(tokens_of(&nft, ALICE), [U256::from(3), U256::from(4)]);
+        assert_eq!(tokens_of(&nft, BOB), [U256::from(5)]);
+    }
+
+    #[test]
+    fn mint_consecutive() {
+        let host = Host::new();
+        let mut nft = host.contract::<TestNFT>();
+
+        let first = Token::mint_consecutive(&mut nft, BOB, U256::from(3)).unwrap_ok();
+        Token::mint(&mut nft, ALICE, U256::from(3)).unwrap_ok();
+
+        assert_eq!(first, U256::ZERO);
+        assert_eq!(nft.enumerable.total_supply().unwrap_ok(), U256::from(4));
+        assert_eq!(tokens_of(&nft, BOB), [U256::ZERO, U256::from(1), U256::from(2)]);
+
+        host.set_sender(BOB);
+        Token::transfer_from(&mut nft, BOB, ALICE, U256::from(1)).unwrap_ok();
+
+        assert_eq!(tokens_of(&nft, BOB), [U256::ZERO, U256::from(2)]);
+        assert_eq!(tokens_of(&nft, ALICE), [U256::from(3), U256::from(1)]);
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let (_host, nft) = setup();
+
+        let revert = nft.enumerable.token_by_index(U256::from(3)).unwrap_revert();
+        assert_eq!(
+            revert,
+            ERC721OutOfBoundsIndex {
+                owner: Address::ZERO,
+                index: U256::from(3),
+            }
+            .encode()
+        );
+
+        let revert = Enumerable::token_of_owner_by_index(&nft, BOB, U256::ZERO).unwrap_revert();
+        assert_eq!(
+            revert,
+            ERC721OutOfBoundsIndex {
+                owner: BOB,
+                index: U256::ZERO,
+            }
+            .encode()
+        );
+    }
+
+    #[test]
+    fn supports_interface() {
+        assert!(Token::supports_interface(0x780e9d63u32.to_be_bytes()).unwrap_ok());
+        assert!(Token::supports_interface(0x5b5e139fu32.to_be_bytes()).unwrap_ok());
+        assert!(Token::supports_interface(0x80ac58cdu32.to_be_bytes()).unwrap_ok());
+        assert!(!Token::supports_interface(0xffffffffu32.to_be_bytes()).unwrap_ok());
+    }
+}
@@ -0,0 +1,297 @@
+//! Provides a storage-backed token URI extension for the [`ERC721`] token.
+//!
+//! The [`ERC721URIStorage`] type lets metadata change after deployment, on a
+//! reveal for instance. `tokenURI` returns, in order of precedence, the URI set
+//! for the token itself, the base URI followed by the token id, or
+//! [`ERC721Params::token_uri`] when neither is set. Every change emits an
+//! ERC-4906 event so that marketplaces refresh their copy of the metadata.
+//!
+//! [`ERC721URIStorage`] is inherited alongside [`ERC721`], and must come first so
+//! that its `tokenURI` takes precedence. The params set
+//! [`ERC721Params::METADATA_UPDATES`] so that `supportsInterface` reports
+//! ERC-4906:
+//!
+//! ```ignore
+//! impl ERC721Params for MyParams {
+//!     // ...
+//!
+//!     const METADATA_UPDATES: bool = true;
+//!
+//!     fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+//!         _storage: &mut S,
+//!         _from: Address,
+//!         to: Address,
+//!         first_id: U256,
+//!         _batch_size: U256,
+//!     ) -> Result<(), Vec<u8>> {
+//!         ERC721URIStorage::<Self>::after_token_transfer(to, first_id);
+//!         Ok(())
+//!     }
+//! }
+//!
+//! sol_storage! {
+//!     #[entrypoint]
+//!     pub struct MyNFT {
+//!         #[borrow]
+//!         ERC721<MyParams> erc721;
+//!         #[borrow]
+//!         ERC721URIStorage<MyParams> uri_storage;
+//!     }
+//! }
+//!
+//! #[external]
+//! #[inherit(ERC721URIStorage<MyParams>, ERC721<MyParams>)]
+//! impl MyNFT {}
+//! ```
+//!
+//! The URIs are only set through the inherent methods, so the inheriting contract
+//! decides who may change them. The params forward
+//! [`ERC721Params::after_token_transfer`] to
+//! [`ERC721URIStorage::after_token_transfer`], which clears the URI of a burned
+//! token so that one minted again under the same id does not inherit it. The URIs
+//! live in the `rustmate.ERC721URIStorage` namespace, see
+//! [`crate::utils::namespace`].
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use crate::tokens::erc721::{
+    ERC721Error,
+    ERC721Params,
+    ERC721,
+};
+use crate::utils::namespace;
+use alloc::{
+    format,
+    string::String,
+};
+use alloy_primitives::{
+    b256,
+    Address,
+    B256,
+    U256,
+};
+use alloy_sol_types::sol;
+use core::{
+    borrow::{
+        Borrow,
+        BorrowMut,
+    },
+    marker::PhantomData,
+};
+use stylus_sdk::{
+    evm,
+    prelude::*,
+};
+
+sol_storage! {
+    /// ERC721URIStorage stores the base URI and per-token URIs of an [`ERC721`]
+    pub struct ERC721URIStorage<T: ERC721Params> {
+        PhantomData<T> phantom;
+    }
+
+    /// The URIs, kept in the `rustmate.ERC721URIStorage` namespace
+    pub struct URIStorage {
+        string base_uri;
+        mapping(uint256 => string) token_uris;
+    }
+}
+
+/// keccak256(abi.encode(uint256(keccak256("rustmate.ERC721URIStorage")) - 1)) & ~bytes32(uint256(0xff))
+const STORAGE_SLOT: B256 =
+    b256!("13386add65f15d8dc7071624e67a354fd38e9a6ff7bfa542512eb922f6943d00");
+
+// Declare events
+sol! {
+    event MetadataUpdate(uint256 _tokenId);
+    event BatchMetadataUpdate(uint256 _fromTokenId, uint256 _toTokenId);
+}
+
+/// Simplifies the result type for the contract's methods.
+type Result<T, E = ERC721Error> = core::result::Result<T, E>;
+
+impl URIStorage {
+    /// Returns the URIs of the token in their namespace.
+    fn load() -> Self {
+        namespace::load(STORAGE_SLOT)
+    }
+}
+
+impl<T: ERC721Params> ERC721URIStorage<T> {
+    /// Sets the URI every token id is appended to, unless it has a URI of its own.
+    /// An empty base falls back to [`ERC721Params::token_uri`].
+    pub fn set_base_uri(&mut self, base_uri: &str) {
+        URIStorage::load().base_uri.set_str(base_uri);
+
+        evm::log(BatchMetadataUpdate {
+            _fromTokenId: U256::ZERO,
+            _toTokenId: U256::MAX,
+        });
+    }
+
+    /// Sets the URI of `id`, which must exist, overriding the base URI.
+    /// An empty URI falls back to the base URI.
+    pub fn set_token_uri<S>(storage: &mut S, id: U256, uri: &str) -> Result<()>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC721<T>>,
+    {
+        Borrow::<ERC721<T>>::borrow(storage).owner_of(id)?;
+
+        URIStorage::load().token_uris.setter(id).set_str(uri);
+
+        evm::log(MetadataUpdate { _tokenId: id });
+
+        Ok(())
+    }
+
+    /// Clears the URI of a burned token. Call it from
+    /// [`ERC721Params::after_token_transfer`]. Batches are only ever minted, so
+    /// a burn always moves a single token.
+    pub fn after_token_transfer(to: Address, id: U256) {
+        if to.is_zero() {
+            URIStorage::load().token_uris.setter(id).set_str("");
+        }
+    }
+}
+
+#[external]
+#[inherit(ERC721<T>)]
+impl<T: ERC721Params> ERC721URIStorage<T> {
+    #[selector(name = "tokenURI")]
+    pub fn token_uri<S>(storage: &S, id: U256) -> Result<String>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC721<T>>,
+    {
+        Borrow::<ERC721<T>>::borrow(storage).owner_of(id)?;
+
+        let uri_storage = URIStorage::load();
+
+        let uri = uri_storage.token_uris.getter(id).get_string();
+        if !uri.is_empty() {
+            return Ok(uri);
+        }
+
+        let base_uri = uri_storage.base_uri.get_string();
+        if !base_uri.is_empty() {
+            return Ok(format!("{base_uri}{id}"));
+        }
+
+        Ok(T::token_uri(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        Host,
+        ResultExt,
+        ALICE,
+    };
+    use crate::tokens::erc721::NotMinted;
+    use alloy_sol_types::SolError;
+
+    struct TestParams;
+
+    impl ERC721Params for TestParams {
+        const NAME: &'static str = "Test NFT";
+        const SYMBOL: &'static str = "TNFT";
+        const METADATA_UPDATES: bool = true;
+
+        fn token_uri(_id: U256) -> String {
+            "ipfs://unrevealed".into()
+        }
+
+        fn after_token_transfer<S: TopLevelStorage + BorrowMut<ERC721<Self>>>(
+            _storage: &mut S,
+            _from: Address,
+            to: Address,
+            first_id: U256,
+            _batch_size: U256,
+        ) -> Result<(), Vec<u8>> {
+            TokenURIs::after_token_transfer(to, first_id);
+            Ok(())
+        }
+    }
+
+    sol_storage! {
+        pub struct TestNFT {
+            #[borrow]
+            ERC721<TestParams> erc721;
+            #[borrow]
+            ERC721URIStorage<TestParams> uri_storage;
+        }
+    }
+
+    unsafe impl TopLevelStorage for TestNFT {}
+
+    type TokenURIs = ERC721URIStorage<TestParams>;
+    type Token = ERC721<TestParams>;
+
+    const ID: U256 = U256::from_limbs([42, 0, 0, 0]);
+
+    fn setup() -> (Host, TestNFT) {
+        let host = Host::new();
+        let mut nft = host.contract::<TestNFT>();
+        Token::mint(&mut nft, ALICE, ID).unwrap_ok();
+        (host, nft)
+    }
+
+    #[test]
+    fn fallback_uri() {
+        let (_host, nft) = setup();
+
+        assert_eq!(TokenURIs::token_uri(&nft, ID).unwrap_ok(), "ipfs://unrevealed");
+
+        let revert = TokenURIs::token_uri(&nft, U256::from(1)).unwrap_revert();
+        assert_eq!(revert, NotMinted {}.encode());
+    }
+
+    #[test]
+    fn base_uri() {
+        let (host, mut nft) = setup();
+
+        nft.uri_storage.set_base_uri("ipfs://revealed/");
+
+        assert_eq!(TokenURIs::token_uri(&nft, ID).unwrap_ok(), "ipfs://revealed/42");
+        assert!(host.emitted(&BatchMetadataUpdate {
+            _fromTokenId: U256::ZERO,
+            _toTokenId: U256::MAX,
+        }));
+    }
+
+    #[test]
+    fn token_uri_override() {
+        let (host, mut nft) = setup();
+        nft.uri_storage.set_base_uri("ipfs://revealed/");
+
+        TokenURIs::set_token_uri(&mut nft, ID, "ipfs://legendary").unwrap_ok();
+
+        assert_eq!(TokenURIs::token_uri(&nft, ID).unwrap_ok(), "ipfs://legendary");
+        assert!(host.emitted(&MetadataUpdate { _tokenId: ID }));
+
+        TokenURIs::set_token_uri(&mut nft, ID, "").unwrap_ok();
+
+        assert_eq!(TokenURIs::token_uri(&nft, ID).unwrap_ok(), "ipfs://revealed/42");
+
+        let revert = TokenURIs::set_token_uri(&mut nft, U256::from(1), "").unwrap_revert();
+        assert_eq!(revert, NotMinted {}.encode());
+    }
+
+    #[test]
+    fn burn_clears_token_uri() {
+        let (_host, mut nft) = setup();
+        TokenURIs::set_token_uri(&mut nft, ID, "ipfs://legendary").unwrap_ok();
+
+        Token::burn(&mut nft, ID).unwrap_ok();
+        Token::mint(&mut nft, ALICE, ID).unwrap_ok();
+
+        assert_eq!(TokenURIs::token_uri(&nft, ID).unwrap_ok(), "ipfs://unrevealed");
+    }
+
+    #[test]
+    fn supports_interface() {
+        assert!(Token::supports_interface(0x49064906u32.to_be_bytes()).unwrap_ok());
+        assert!(Token::supports_interface(0x80ac58cdu32.to_be_bytes()).unwrap_ok());
+        assert!(!Token::supports_interface(0xb45a3c0eu32.to_be_bytes()).unwrap_ok());
+    }
+}
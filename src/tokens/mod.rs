@@ -1,5 +1,17 @@
 pub mod erc1155;
+pub mod erc1155_supply;
+pub mod erc1155_uri_storage;
+pub mod erc1363;
 pub mod erc20;
+pub mod erc20_snapshot;
+pub mod erc20_votes;
+pub mod erc4907;
+pub mod erc5192;
 pub mod erc6909;
+pub mod erc6909_content_uri;
+pub mod erc6909_metadata;
 pub mod erc721;
-// pub mod weth;
+pub mod erc721_enumerable;
+pub mod erc721_uri_storage;
+pub mod royalty;
+pub mod weth;
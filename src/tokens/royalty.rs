@@ -0,0 +1,353 @@
+//! Provides an implementation of the ERC-2981 NFT royalty standard.
+//!
+//! The [`ERC2981`] type answers `royaltyInfo` with a default receiver and fee,
+//! which can be overridden for individual tokens. Fees are in basis points of
+//! the sale price. It is intended to be inherited alongside [`ERC721`] or
+//! [`ERC1155`], whose `ROYALTIES` parameter makes `supportsInterface` report it:
+//!
+//! ```ignore
+//! sol_storage! {
+//!     #[entrypoint]
+//!     pub struct MyNFT {
+//!         #[borrow]
+//!         ERC721<MyParams> erc721;
+//!         #[borrow]
+//!         ERC2981 royalty;
+//!     }
+//! }
+//!
+//! #[external]
+//! #[inherit(ERC721<MyParams>, ERC2981)]
+//! impl MyNFT {}
+//! ```
+//!
+//! The royalties are only set through the inherent methods, so the inheriting
+//! contract decides who may change them. Marketplaces are expected, not forced,
+//! to pay them.
+//!
+//! [`ERC721`]: crate::tokens::erc721::ERC721
+//! [`ERC1155`]: crate::tokens::erc1155::ERC1155
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use alloc::vec::Vec;
+use alloy_primitives::{
+    Address,
+    Uint,
+    U256,
+};
+use alloy_sol_types::{
+    sol,
+    SolError,
+};
+use stylus_sdk::prelude::*;
+
+/// The denominator of royalty fees, which are in basis points.
+pub const FEE_DENOMINATOR: u64 = 10_000;
+
+sol_storage! {
+    /// ERC2981 stores the royalties of a token collection
+    pub struct ERC2981 {
+        RoyaltyInfo default_royalty;
+        mapping(uint256 => RoyaltyInfo) token_royalty;
+    }
+
+    /// A royalty receiver and its fee in basis points
+    pub struct RoyaltyInfo {
+        address receiver;
+        uint96 fee_bps;
+    }
+}
+
+// Declare Solidity error types
+sol! {
+    error ERC2981InvalidDefaultRoyalty(uint256 numerator, uint256 denominator);
+    error ERC2981InvalidDefaultRoyaltyReceiver(address receiver);
+    error ERC2981InvalidTokenRoyalty(uint256 tokenId, uint256 numerator, uint256 denominator);
+    error ERC2981InvalidTokenRoyaltyReceiver(uint256 tokenId, address receiver);
+}
+
+/// Represents the ways methods may fail.
+pub enum ERC2981Error {
+    ERC2981InvalidDefaultRoyalty(ERC2981InvalidDefaultRoyalty),
+    ERC2981InvalidDefaultRoyaltyReceiver(ERC2981InvalidDefaultRoyaltyReceiver),
+    ERC2981InvalidTokenRoyalty(ERC2981InvalidTokenRoyalty),
+    ERC2981InvalidTokenRoyaltyReceiver(ERC2981InvalidTokenRoyaltyReceiver),
+}
+
+/// We will soon provide a `#[derive(SolidityError)]` to clean this up.
+impl From<ERC2981Error> for Vec<u8> {
+    fn from(val: ERC2981Error) -> Self {
+        match val {
+            ERC2981Error::ERC2981InvalidDefaultRoyalty(err) => err.encode(),
+            ERC2981Error::ERC2981InvalidDefaultRoyaltyReceiver(err) => err.encode(),
+            ERC2981Error::ERC2981InvalidTokenRoyalty(err) => err.encode(),
+            ERC2981Error::ERC2981InvalidTokenRoyaltyReceiver(err) => err.encode(),
+        }
+    }
+}
+
+/// Simplifies the result type for the contract's methods.
+type Result<T, E = ERC2981Error> = core::result::Result<T, E>;
+
+impl RoyaltyInfo {
+    fn set(&mut self, receiver: Address, fee_bps: u64) {
+        self.receiver.set(receiver);
+        self.fee_bps.set(Uint::from(fee_bps));
+    }
+
+    fn clear(&mut self) {
+        self.receiver.set(Address::ZERO);
+        self.fee_bps.set(Uint::ZERO);
+    }
+}
+
+impl ERC2981 {
+    /// Sets the royalty of every token without one of its own.
+    pub fn set_default_royalty(&mut self, receiver: Address, fee_bps: u64) -> Result<()> {
+        if fee_bps > FEE_DENOMINATOR {
+            return Err(ERC2981Error::ERC2981InvalidDefaultRoyalty(
+                ERC2981InvalidDefaultRoyalty {
+                    numerator: U256::from(fee_bps),
+                    denominator: U256::from(FEE_DENOMINATOR),
+                },
+            ));
+        }
+
+        if receiver.is_zero() {
+            return Err(ERC2981Error::ERC2981InvalidDefaultRoyaltyReceiver(
+                ERC2981InvalidDefaultRoyaltyReceiver { receiver },
+            ));
+        }
+
+        self.default_royalty.set(receiver, fee_bps);
+        Ok(())
+    }
+
+    pub fn delete_default_royalty(&mut self) {
+        self.default_royalty.clear();
+    }
+
+    /// Sets the royalty of `id`, overriding the default one.
+    pub fn set_token_royalty(&mut self, id: U256, receiver: Address, fee_bps: u64) -> Result<()> {
+        if fee_bps > FEE_DENOMINATOR {
+            return Err(ERC2981Error::ERC2981InvalidTokenRoyalty(
+                ERC2981InvalidTokenRoyalty {
+                    tokenId: id,
+                    numerator: U256::from(fee_bps),
+                    denominator: U256::from(FEE_DENOMINATOR),
+                },
+            ));
+        }
+
+        if receiver.is_zero() {
+            return Err(ERC2981Error::ERC2981InvalidTokenRoyaltyReceiver(
+                ERC2981InvalidTokenRoyaltyReceiver {
+                    tokenId: id,
+                    receiver,
+                },
+            ));
+        }
+
+        self.token_royalty.setter(id).set(receiver, fee_bps);
+        Ok(())
+    }
+
+    /// Makes `id` fall back to the default royalty.
+    pub fn reset_token_royalty(&mut self, id: U256) {
+        self.token_royalty.setter(id).clear();
+    }
+}
+
+#[external]
+impl ERC2981 {
+    pub fn royalty_info(&self, id: U256, sale_price: U256) -> Result<(Address, U256)> {
+        let token_royalty = self.token_royalty.getter(id);
+
+        let (receiver, fee_bps) = match token_royalty.receiver.get() {
+            receiver if receiver.is_zero() => (
+                self.default_royalty.receiver.get(),
+                self.default_royalty.fee_bps.get(),
+            ),
+            receiver => (receiver, token_royalty.fee_bps.get()),
+        };
+
+        // The fee never exceeds the denominator, so splitting the price this way
+        // gives the exact `sale_price * fee_bps / FEE_DENOMINATOR` without the
+        // product overflowing for large prices.
+        let fee_bps = U256::from(fee_bps);
+        let denominator = U256::from(FEE_DENOMINATOR);
+        let amount =
+            sale_price / denominator * fee_bps + sale_price % denominator * fee_bps / denominator;
+        Ok((receiver, amount))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        Host,
+        ResultExt,
+        ALICE,
+        BOB,
+    };
+    use crate::tokens::erc1155::{
+        ERC1155Params,
+        ERC1155,
+    };
+    use crate::tokens::erc721::{
+        ERC721Params,
+        ERC721,
+    };
+    use alloc::string::String;
+
+    #[test]
+    fn default_royalty() {
+        let host = Host::new();
+        let mut royalty = host.contract::<ERC2981>();
+
+        assert_eq!(
+            royalty.royalty_info(U256::from(1), U256::from(1_000)).unwrap_ok(),
+            (Address::ZERO, U256::ZERO)
+        );
+
+        royalty.set_default_royalty(ALICE, 250).unwrap_ok();
+
+        assert_eq!(
+            royalty.royalty_info(U256::from(1), U256::from(1_000)).unwrap_ok(),
+            (ALICE, U256::from(25))
+        );
+
+        royalty.delete_default_royalty();
+
+        assert_eq!(
+            royalty.royalty_info(U256::from(1), U256::from(1_000)).unwrap_ok(),
+            (Address::ZERO, U256::ZERO)
+        );
+    }
+
+    #[test]
+    fn token_royalty() {
+        let host = Host::new();
+        let mut royalty = host.contract::<ERC2981>();
+        royalty.set_default_royalty(ALICE, 250).unwrap_ok();
+
+        royalty.set_token_royalty(U256::from(1), BOB, 1_000).unwrap_ok();
+
+        assert_eq!(
+            royalty.royalty_info(U256::from(1), U256::from(1_000)).unwrap_ok(),
+            (BOB, U256::from(100))
+        );
+        assert_eq!(
+            royalty.royalty_info(U256::from(2), U256::from(1_000)).unwrap_ok(),
+            (ALICE, U256::from(25))
+        );
+
+        royalty.reset_token_royalty(U256::from(1));
+
+        assert_eq!(
+            royalty.royalty_info(U256::from(1), U256::from(1_000)).unwrap_ok(),
+            (ALICE, U256::from(25))
+        );
+    }
+
+    #[test]
+    fn large_sale_price() {
+        let host = Host::new();
+        let mut royalty = host.contract::<ERC2981>();
+        royalty.set_default_royalty(ALICE, 250).unwrap_ok();
+
+        // floor((2^256 - 1) * 250 / 10_000), computed at full width.
+        let expected = U256::MAX / U256::from(40);
+        assert_eq!(
+            royalty.royalty_info(U256::from(1), U256::MAX).unwrap_ok(),
+            (ALICE, expected)
+        );
+
+        royalty.set_token_royalty(U256::from(1), BOB, 10_000).unwrap_ok();
+
+        assert_eq!(
+            royalty.royalty_info(U256::from(1), U256::MAX).unwrap_ok(),
+            (BOB, U256::MAX)
+        );
+    }
+
+    #[test]
+    fn invalid_royalty() {
+        let host = Host::new();
+        let mut royalty = host.contract::<ERC2981>();
+
+        let revert = royalty.set_default_royalty(ALICE, 10_001).unwrap_revert();
+        assert_eq!(
+            revert,
+            ERC2981InvalidDefaultRoyalty {
+                numerator: U256::from(10_001),
+                denominator: U256::from(10_000),
+            }
+            .encode()
+        );
+
+        let revert = royalty.set_default_royalty(Address::ZERO, 250).unwrap_revert();
+        assert_eq!(
+            revert,
+            ERC2981InvalidDefaultRoyaltyReceiver {
+                receiver: Address::ZERO
+            }
+            .encode()
+        );
+
+        let revert = royalty
+            .set_token_royalty(U256::from(1), ALICE, 10_001)
+            .unwrap_revert();
+        assert_eq!(
+            revert,
+            ERC2981InvalidTokenRoyalty {
+                tokenId: U256::from(1),
+                numerator: U256::from(10_001),
+                denominator: U256::from(10_000),
+            }
+            .encode()
+        );
+
+        let revert = royalty
+            .set_token_royalty(U256::from(1), Address::ZERO, 250)
+            .unwrap_revert();
+        assert_eq!(
+            revert,
+            ERC2981InvalidTokenRoyaltyReceiver {
+                tokenId: U256::from(1),
+                receiver: Address::ZERO,
+            }
+            .encode()
+        );
+    }
+
+    struct RoyaltyParams;
+
+    impl ERC721Params for RoyaltyParams {
+        const NAME: &'static str = "Royalty NFT";
+        const SYMBOL: &'static str = "RNFT";
+        const ROYALTIES: bool = true;
+
+        fn token_uri(_id: U256) -> String {
+            String::new()
+        }
+    }
+
+    impl ERC1155Params for RoyaltyParams {
+        const ROYALTIES: bool = true;
+
+        fn uri(_id: U256) -> String {
+            String::new()
+        }
+    }
+
+    #[test]
+    fn supports_interface() {
+        let interface = 0x2a55205au32.to_be_bytes();
+
+        assert!(ERC721::<RoyaltyParams>::supports_interface(interface).unwrap_ok());
+        assert!(ERC1155::<RoyaltyParams>::supports_interface(interface).unwrap_ok());
+    }
+}
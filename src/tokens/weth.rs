@@ -1,34 +1,66 @@
-#![cfg_attr(not(feature = "export-abi"), no_main, no_std)]
-extern crate alloc;
+//! Provides an implementation of Wrapped Ether.
+//!
+//! The [`WETH`] type adds `deposit` and `withdraw` to an [`ERC20`] token over
+//! [`WETHParams`], minting one token for each wei sent and burning it to send the
+//! wei back. Flash mints are enabled, so the token can be borrowed through the
+//! ERC-3156 methods of [`ERC20`]. It is inherited alongside [`ERC20`]:
+//!
+//! ```ignore
+//! sol_storage! {
+//!     #[entrypoint]
+//!     pub struct MyWETH {
+//!         #[borrow]
+//!         ERC20<WETHParams> erc20;
+//!         #[borrow]
+//!         WETH weth;
+//!     }
+//! }
+//!
+//! #[external]
+//! #[inherit(WETH, ERC20<WETHParams>)]
+//! impl MyWETH {}
+//! ```
+//!
+//! Note that this code is unaudited and not fit for production use.
 
-mod erc20;
-
-use create::erc20::{ERC20Params, ERC20};
+use crate::host::msg;
+use crate::tokens::erc20::{
+    ERC20Params,
+    ERC20,
+};
 use alloc::vec::Vec;
-use alloy_primitives::{B256, U256};
+use alloy_primitives::{
+    B256,
+    U256,
+};
 use alloy_sol_types::sol;
-use stylus_sdk::{call, evm, msg, prelude::*};
-
-#[global_allocator]
-static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
-
-struct WETHParams;
+use core::{
+    borrow::BorrowMut,
+    marker::PhantomData,
+};
+use stylus_sdk::{
+    call,
+    evm,
+    prelude::*,
+};
 
 /// Immutable definitions
+pub struct WETHParams;
+
 impl ERC20Params for WETHParams {
     const NAME: &'static str = "Wrapped Ether";
     const SYMBOL: &'static str = "WETH";
     const DECIMALS: u8 = 18;
-    const INITIAL_CHAIN_ID: u64 = 1;
+    // The separator commits to the deployed address, so it is always computed.
+    const INITIAL_CHAIN_ID: u64 = 0;
     const INITIAL_DOMAIN_SEPARATOR: B256 = B256::ZERO;
+    const FLASH_MINT: bool = true;
 }
 
-// The contract
 sol_storage! {
-    #[entrypoint] // Makes WETH the entrypoint
-    struct WETH {
-        #[borrow] // Allows ERC20 to access WETH's storage and make calls
-        ERC20<WETHParams> erc20;
+    /// WETH implements the deposit and withdrawal methods on top of [`ERC20`]
+    pub struct WETH {
+        PhantomData<WETHParams> phantom;
     }
 }
 
@@ -41,8 +73,11 @@ sol! {
 #[inherit(ERC20<WETHParams>)]
 impl WETH {
     #[payable]
-    pub fn deposit(&mut self) -> Result<(), Vec<u8>> {
-        self.erc20.mint(msg::sender(), msg::value());
+    pub fn deposit<S>(storage: &mut S) -> Result<(), Vec<u8>>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC20<WETHParams>>,
+    {
+        ERC20::<WETHParams>::mint(storage, msg::sender(), msg::value())?;
 
         evm::log(Deposit {
             from: msg::sender(),
@@ -52,19 +87,130 @@ impl WETH {
         Ok(())
     }
 
-    pub fn withdraw(&mut self, amount: U256) -> Result<(), Vec<u8>> {
-        self.erc20.burn(msg::sender(), amount);
+    pub fn withdraw<S>(storage: &mut S, amount: U256) -> Result<(), Vec<u8>>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC20<WETHParams>>,
+    {
+        ERC20::<WETHParams>::burn(storage, msg::sender(), amount)?;
 
         evm::log(Withdrawal {
             to: msg::sender(),
-            amount: amount,
+            amount,
         });
 
         call::transfer_eth(msg::sender(), amount)
     }
 
     #[payable]
-    pub fn receive(&mut self) -> Result<(), Vec<u8>> {
-        self.deposit()
+    pub fn receive<S>(storage: &mut S) -> Result<(), Vec<u8>>
+    where
+        S: TopLevelStorage + BorrowMut<Self> + BorrowMut<ERC20<WETHParams>>,
+    {
+        Self::deposit(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        Host,
+        ResultExt,
+        ALICE,
+    };
+    use crate::tokens::erc20::ERC20InsufficientBalance;
+    use alloy_primitives::{
+        address,
+        Address,
+    };
+    use alloy_sol_types::SolError;
+
+    sol_storage! {
+        pub struct TestWETH {
+            #[borrow]
+            ERC20<WETHParams> erc20;
+            #[borrow]
+            WETH weth;
+        }
+    }
+
+    unsafe impl TopLevelStorage for TestWETH {}
+
+    const TOKEN: Address = address!("000000000000000000000000000000000000beef");
+
+    fn setup() -> (Host, TestWETH) {
+        let host = Host::new();
+        let token = host.contract::<TestWETH>();
+        host.set_contract_address(TOKEN);
+        host.set_sender(ALICE);
+        (host, token)
+    }
+
+    /// Sends `value` wei to `deposit`, crediting the contract as the chain would.
+    fn deposit(host: &Host, token: &mut TestWETH, value: u64) {
+        host.set_value(U256::from(value));
+        host.set_balance(TOKEN, host.balance(TOKEN) + U256::from(value));
+        WETH::deposit(token).unwrap_ok();
+        host.set_value(U256::ZERO);
+    }
+
+    #[test]
+    fn deposit_mints() {
+        let (host, mut token) = setup();
+
+        deposit(&host, &mut token, 1_000);
+
+        assert_eq!(token.erc20.balance_of(ALICE).unwrap_ok(), U256::from(1_000));
+        assert_eq!(token.erc20.total_supply().unwrap_ok(), U256::from(1_000));
+        assert!(host.emitted(&Deposit {
+            from: ALICE,
+            amount: U256::from(1_000),
+        }));
+    }
+
+    #[test]
+    fn withdraw_burns_and_sends() {
+        let (host, mut token) = setup();
+
+        deposit(&host, &mut token, 1_000);
+        WETH::withdraw(&mut token, U256::from(400)).unwrap_ok();
+
+        assert_eq!(token.erc20.balance_of(ALICE).unwrap_ok(), U256::from(600));
+        assert_eq!(host.balance(ALICE), U256::from(400));
+        assert_eq!(host.balance(TOKEN), U256::from(600));
+        assert!(host.emitted(&Withdrawal {
+            to: ALICE,
+            amount: U256::from(400),
+        }));
+    }
+
+    #[test]
+    fn withdraw_more_than_balance() {
+        let (host, mut token) = setup();
+
+        deposit(&host, &mut token, 1_000);
+        let revert = WETH::withdraw(&mut token, U256::from(1_001)).unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ERC20InsufficientBalance {
+                sender: ALICE,
+                balance: U256::from(1_000),
+                needed: U256::from(1_001),
+            }
+            .encode()
+        );
+        assert_eq!(host.balance(TOKEN), U256::from(1_000));
+    }
+
+    #[test]
+    fn flash_mint_enabled() {
+        let (_host, token) = setup();
+
+        assert_eq!(token.erc20.max_flash_loan(TOKEN).unwrap_ok(), U256::MAX);
+        assert_eq!(
+            token.erc20.flash_fee(TOKEN, U256::from(1_000)).unwrap_ok(),
+            U256::ZERO
+        );
     }
 }
@@ -0,0 +1,61 @@
+//! Provides an implementation of Base64 encoding, as described in RFC 4648.
+//!
+//! [`encode`] is typically used to embed JSON metadata or an SVG image in a
+//! `data:` URI, so that token URIs can be served fully on chain.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use alloc::{
+    string::String,
+    vec::Vec,
+};
+
+const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes `data` in Base64 with `=` padding.
+pub fn encode(data: &[u8]) -> String {
+    let mut encoded = Vec::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
+        let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+        let triple = (b0 << 16) | (b1 << 8) | b2;
+
+        encoded.push(TABLE[(triple >> 18) as usize & 0x3f]);
+        encoded.push(TABLE[(triple >> 12) as usize & 0x3f]);
+        encoded.push(match chunk.len() > 1 {
+            true => TABLE[(triple >> 6) as usize & 0x3f],
+            false => b'=',
+        });
+        encoded.push(match chunk.len() > 2 {
+            true => TABLE[triple as usize & 0x3f],
+            false => b'=',
+        });
+    }
+
+    // Every byte pushed comes from the ASCII table above.
+    unsafe { String::from_utf8_unchecked(encoded) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The test vectors from RFC 4648.
+    #[test]
+    fn encode_rfc_vectors() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"foo"), "Zm9v");
+        assert_eq!(encode(b"foob"), "Zm9vYg==");
+        assert_eq!(encode(b"fooba"), "Zm9vYmE=");
+        assert_eq!(encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn encode_high_bytes() {
+        assert_eq!(encode(&[0xfb, 0xff, 0xbf]), "+/+/");
+    }
+}
@@ -5,6 +5,7 @@
 //!
 //! Note that this code is unaudited and not fit for production use.
 
+use crate::host::contract;
 use alloc::vec::Vec;
 use alloy_primitives::{
     Address,
@@ -19,7 +20,6 @@ use alloy_sol_types::{
 };
 use core::marker::PhantomData;
 use stylus_sdk::call::RawCall;
-use stylus_sdk::contract;
 use stylus_sdk::crypto;
 use stylus_sdk::deploy::RawDeploy;
 use stylus_sdk::{
@@ -0,0 +1,231 @@
+//! Provides an implementation of ECDSA signature recovery.
+//!
+//! [`recover`] and [`recover_bytes`] return the address that signed a hash,
+//! and reject malleable signatures: those with an `s` in the upper half of the
+//! curve order, or a `v` other than 27 or 28. [`recover_bytes`] also accepts
+//! EIP-2098 compact signatures.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use alloc::vec::Vec;
+use alloy_primitives::{
+    address,
+    Address,
+    B256,
+    U256,
+};
+use alloy_sol_types::{
+    sol,
+    sol_data,
+    SolError,
+    SolType,
+};
+use stylus_sdk::call::RawCall;
+use stylus_sdk::crypto;
+use stylus_sdk::evm;
+
+/// Half of the secp256k1 curve order, the largest `s` accepted in a signature.
+const SECP256K1N_HALF: U256 = U256::from_limbs([
+    0xdfe92f46681b20a0,
+    0x5d576e7357a4501d,
+    0xffffffffffffffff,
+    0x7fffffffffffffff,
+]);
+
+/// The address of the `ecrecover` precompile.
+const ECRECOVER: Address = address!("0000000000000000000000000000000000000001");
+
+sol! {
+    error ECDSAInvalidSignature();
+    error ECDSAInvalidSignatureLength(uint256 length);
+    error ECDSAInvalidSignatureS(bytes32 s);
+    error ECDSAInvalidSignatureV(uint8 v);
+}
+
+/// Represents the ways signature recovery may fail.
+pub enum ECDSAError {
+    InvalidSignature(ECDSAInvalidSignature),
+    InvalidSignatureLength(ECDSAInvalidSignatureLength),
+    InvalidSignatureS(ECDSAInvalidSignatureS),
+    InvalidSignatureV(ECDSAInvalidSignatureV),
+}
+
+/// We will soon provide a `#[derive(SolidityError)]` to clean this up.
+impl From<ECDSAError> for Vec<u8> {
+    fn from(val: ECDSAError) -> Self {
+        match val {
+            ECDSAError::InvalidSignature(err) => err.encode(),
+            ECDSAError::InvalidSignatureLength(err) => err.encode(),
+            ECDSAError::InvalidSignatureS(err) => err.encode(),
+            ECDSAError::InvalidSignatureV(err) => err.encode(),
+        }
+    }
+}
+
+/// Simplifies the result type for the library's functions.
+type Result<T, E = ECDSAError> = core::result::Result<T, E>;
+
+/// Recovers the signer of `hash` through the `ecrecover` precompile.
+pub fn recover(hash: B256, v: u8, r: B256, s: B256) -> Result<Address> {
+    // Reject signatures in the upper half of the curve order, which are malleable.
+    if U256::from_be_bytes(s.0) > SECP256K1N_HALF {
+        return Err(ECDSAError::InvalidSignatureS(ECDSAInvalidSignatureS { s: *s }));
+    }
+
+    if v != 27 && v != 28 {
+        return Err(ECDSAError::InvalidSignatureV(ECDSAInvalidSignatureV { v }));
+    }
+
+    let data = <sol! { (bytes32, uint8, bytes32, bytes32) }>::encode(&(*hash, v, *r, *s));
+
+    // The precompile returns no data for an unrecoverable signature.
+    let signer = RawCall::new_static()
+        .gas(evm::gas_left())
+        .call(ECRECOVER, &data)
+        .ok()
+        .and_then(|ret| sol_data::Address::decode_single(ret.as_slice(), false).ok())
+        .unwrap_or_default();
+
+    if signer.is_zero() {
+        return Err(ECDSAError::InvalidSignature(ECDSAInvalidSignature {}));
+    }
+
+    Ok(signer)
+}
+
+/// Recovers the signer of `hash` from a 65-byte `r || s || v` signature or a
+/// 64-byte EIP-2098 `r || vs` compact signature.
+pub fn recover_bytes(hash: B256, signature: &[u8]) -> Result<Address> {
+    match signature.len() {
+        65 => {
+            let r = B256::from_slice(&signature[0..32]);
+            let s = B256::from_slice(&signature[32..64]);
+            recover(hash, signature[64], r, s)
+        }
+        64 => {
+            // The top bit of `vs` holds the parity of `v`, and the rest is `s`.
+            let r = B256::from_slice(&signature[0..32]);
+            let mut s = B256::from_slice(&signature[32..64]);
+            let v = 27 + (s[0] >> 7);
+            s[0] &= 0x7f;
+            recover(hash, v, r, s)
+        }
+        length => Err(ECDSAError::InvalidSignatureLength(
+            ECDSAInvalidSignatureLength {
+                length: U256::from(length),
+            },
+        )),
+    }
+}
+
+/// Returns the hash signed by `eth_sign` for the 32-byte `hash`, as described
+/// in EIP-191.
+pub fn to_eth_signed_message_hash(hash: B256) -> B256 {
+    let mut message = [0u8; 28 + 32];
+    message[0..28].copy_from_slice(b"\x19Ethereum Signed Message:\n32");
+    message[28..60].copy_from_slice(&hash[..]);
+
+    crypto::keccak(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+    use alloy_primitives::b256;
+
+    const KEY: B256 = B256::repeat_byte(0x42);
+    const HASH: B256 = B256::repeat_byte(0xab);
+
+    fn signature_bytes(v: u8, r: B256, s: B256) -> Vec<u8> {
+        [&r[..], &s[..], &[v]].concat()
+    }
+
+    #[test]
+    fn recover_signer() {
+        let _host = Host::new();
+        let (v, r, s) = sign(KEY, HASH);
+
+        assert_eq!(recover(HASH, v, r, s).unwrap_ok(), signer(KEY));
+        assert_eq!(
+            recover_bytes(HASH, &signature_bytes(v, r, s)).unwrap_ok(),
+            signer(KEY)
+        );
+    }
+
+    #[test]
+    fn recover_compact_signature() {
+        let _host = Host::new();
+        let (v, r, mut vs) = sign(KEY, HASH);
+        if v == 28 {
+            vs[0] |= 0x80;
+        }
+
+        let signature = [&r[..], &vs[..]].concat();
+
+        assert_eq!(recover_bytes(HASH, &signature).unwrap_ok(), signer(KEY));
+    }
+
+    #[test]
+    fn recover_high_s() {
+        let _host = Host::new();
+        let (v, r, s) = sign(KEY, HASH);
+
+        // The same signature, mirrored into the upper half of the curve order.
+        let n = U256::from_be_bytes(
+            b256!("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141").0,
+        );
+        let high_s = B256::from(n - U256::from_be_bytes(s.0));
+        let high_v = if v == 27 { 28 } else { 27 };
+
+        let revert = recover(HASH, high_v, r, high_s).unwrap_revert();
+
+        assert_eq!(revert, ECDSAInvalidSignatureS { s: *high_s }.encode());
+    }
+
+    #[test]
+    fn recover_invalid_v() {
+        let _host = Host::new();
+        let (v, r, s) = sign(KEY, HASH);
+
+        let revert = recover(HASH, v - 27, r, s).unwrap_revert();
+
+        assert_eq!(revert, ECDSAInvalidSignatureV { v: v - 27 }.encode());
+    }
+
+    #[test]
+    fn recover_unrecoverable() {
+        let _host = Host::new();
+
+        let revert = recover(HASH, 27, B256::ZERO, B256::ZERO).unwrap_revert();
+
+        assert_eq!(revert, ECDSAInvalidSignature {}.encode());
+    }
+
+    #[test]
+    fn recover_invalid_length() {
+        let _host = Host::new();
+        let (v, r, s) = sign(KEY, HASH);
+        let mut signature = signature_bytes(v, r, s);
+        signature.push(0);
+
+        let revert = recover_bytes(HASH, &signature).unwrap_revert();
+
+        assert_eq!(
+            revert,
+            ECDSAInvalidSignatureLength {
+                length: U256::from(66)
+            }
+            .encode()
+        );
+    }
+
+    #[test]
+    fn eth_signed_message_hash() {
+        // The EIP-191 hash of `HASH` as a 32-byte message, computed with OpenSSL's KECCAK-256.
+        assert_eq!(
+            to_eth_signed_message_hash(HASH),
+            b256!("e2ff0e36e69683e8cbb0159e90e7d319e743e22b0a37c17566b625eb25102fed")
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Provides an implementation of EIP-712 typed structured data hashing.
+//!
+//! A [`Domain`] describes who a signature is meant for, and [`hash_typed_data`]
+//! combines its separator with the [`hash_struct`] of a message built with
+//! `alloy_sol_types::sol!`. The [`EIP712`] type caches the separator for the
+//! chain it was computed on, and is intended to be embedded in other contract types.
+//!
+//! [`Domain::eip712_domain`] returns the fields of ERC-5267's `eip712Domain()`.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use crate::host::{
+    block,
+    contract,
+};
+use alloc::{
+    string::String,
+    vec::Vec,
+};
+use alloy_primitives::{
+    Address,
+    FixedBytes,
+    B256,
+    U256,
+    U64,
+};
+use alloy_sol_types::{
+    sol,
+    SolStruct,
+    SolType,
+};
+use stylus_sdk::crypto;
+use stylus_sdk::prelude::*;
+
+/// The ERC-5267 bitmap of the fields a [`Domain`] uses: name, version, chain id
+/// and verifying contract.
+pub const DOMAIN_FIELDS: FixedBytes<1> = FixedBytes([0x0f]);
+
+sol! {
+    event EIP712DomainChanged();
+}
+
+/// An EIP-712 domain made of a name, version, chain id and verifying contract.
+pub struct Domain {
+    pub name: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub verifying_contract: Address,
+}
+
+impl Domain {
+    /// Returns the domain of the running contract on the current chain.
+    pub fn new(name: String, version: String) -> Self {
+        Self {
+            name,
+            version,
+            chain_id: block::chainid(),
+            verifying_contract: contract::address(),
+        }
+    }
+
+    pub fn separator(&self) -> B256 {
+        let digest_input = <sol! { (bytes32, bytes32, bytes32, uint256, address) }>::encode(&(
+            *crypto::keccak(b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)"),
+            *crypto::keccak(self.name.as_bytes()),
+            *crypto::keccak(self.version.as_bytes()),
+            U256::from(self.chain_id),
+            self.verifying_contract,
+        ));
+
+        crypto::keccak(digest_input)
+    }
+
+    /// Returns the return values of ERC-5267's `eip712Domain()` for this domain.
+    #[allow(clippy::type_complexity)]
+    pub fn eip712_domain(self) -> (FixedBytes<1>, String, String, U256, Address, B256, Vec<U256>) {
+        (
+            DOMAIN_FIELDS,
+            self.name,
+            self.version,
+            U256::from(self.chain_id),
+            self.verifying_contract,
+            B256::ZERO,
+            Vec::new(),
+        )
+    }
+}
+
+/// Returns the EIP-712 `hashStruct` of a struct declared with `sol!`.
+///
+/// Only structs whose members are not themselves structs are supported, since
+/// `alloy_sol_types` does not yet encode nested structs as their `hashStruct`.
+pub fn hash_struct<S: SolStruct>(value: &S) -> B256 {
+    value.eip712_hash_struct()
+}
+
+/// Returns the digest to sign for a message with `struct_hash` in the domain
+/// with `domain_separator`.
+pub fn hash_typed_data(domain_separator: B256, struct_hash: B256) -> B256 {
+    let mut digest_input = [0u8; 2 + 32 + 32];
+    digest_input[0] = 0x19;
+    digest_input[1] = 0x01;
+    digest_input[2..34].copy_from_slice(&domain_separator[..]);
+    digest_input[34..66].copy_from_slice(&struct_hash[..]);
+
+    crypto::keccak(digest_input)
+}
+
+sol_storage! {
+    /// EIP712 caches a domain separator along with the chain it was computed on.
+    pub struct EIP712 {
+        uint64 cached_chain_id;
+        bytes32 cached_domain_separator;
+    }
+}
+
+impl EIP712 {
+    /// Caches the separator of `domain`, which should be on the current chain.
+    pub fn cache(&mut self, domain: &Domain) {
+        self.cached_chain_id.set(U64::from(domain.chain_id));
+        self.cached_domain_separator.set(domain.separator());
+    }
+
+    /// Returns the cached separator, unless nothing was cached or the chain id
+    /// has changed since, in which case the domain has to be recomputed.
+    pub fn cached_domain_separator(&self) -> Option<B256> {
+        let separator = self.cached_domain_separator.get();
+
+        if separator.is_zero() || self.cached_chain_id.get() != U64::from(block::chainid()) {
+            return None;
+        }
+
+        Some(separator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+    use alloy_primitives::{
+        address,
+        b256,
+    };
+
+    sol! {
+        struct Person {
+            string name;
+            address wallet;
+        }
+    }
+
+    fn mail_domain() -> Domain {
+        Domain {
+            name: "Ether Mail".into(),
+            version: "1".into(),
+            chain_id: 1,
+            verifying_contract: address!("cccccccccccccccccccccccccccccccccccccccc"),
+        }
+    }
+
+    // The "Ether Mail" example from EIP-712.
+    #[test]
+    fn hash_known_vector() {
+        let cow = Person {
+            name: "Cow".into(),
+            wallet: address!("cd2a3d9f938e13cd947ec05abc7fe734df8dd826"),
+        };
+        let mail_hash = b256!("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e");
+
+        assert_eq!(
+            hash_struct(&cow),
+            b256!("fc71e5fa27ff56c350aa531bc129ebdf613b772b6604664f5d8dbe21b85eb0c8")
+        );
+        assert_eq!(
+            mail_domain().separator(),
+            b256!("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+        assert_eq!(
+            hash_typed_data(mail_domain().separator(), mail_hash),
+            b256!("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+        );
+    }
+
+    #[test]
+    fn domain_of_running_contract() {
+        let host = Host::new();
+        host.set_chain_id(1);
+        host.set_contract_address(address!("cccccccccccccccccccccccccccccccccccccccc"));
+
+        let domain = Domain::new("Ether Mail".into(), "1".into());
+
+        assert_eq!(domain.separator(), mail_domain().separator());
+        assert_eq!(
+            domain.eip712_domain(),
+            (
+                DOMAIN_FIELDS,
+                "Ether Mail".into(),
+                "1".into(),
+                U256::from(1),
+                address!("cccccccccccccccccccccccccccccccccccccccc"),
+                B256::ZERO,
+                Vec::new(),
+            )
+        );
+    }
+
+    #[test]
+    fn cache_until_chain_id_changes() {
+        let host = Host::new();
+        let mut eip712 = host.contract::<EIP712>();
+        host.set_chain_id(1);
+
+        assert_eq!(eip712.cached_domain_separator(), None);
+
+        eip712.cache(&mail_domain());
+        assert_eq!(
+            eip712.cached_domain_separator(),
+            Some(mail_domain().separator())
+        );
+
+        host.set_chain_id(2);
+        assert_eq!(eip712.cached_domain_separator(), None);
+    }
+}
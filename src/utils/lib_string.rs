@@ -0,0 +1,220 @@
+//! Provides string helpers for building token metadata on chain.
+//!
+//! [`to_string`], [`to_hex_string`] and [`to_hex_digits`] format numbers,
+//! [`to_checksum_hex_string`] formats addresses as described in EIP-55, and
+//! [`escape_json`] makes any string safe to embed in a JSON value. Combined with
+//! [`base64::encode`], they build fully on-chain token URIs:
+//!
+//! ```ignore
+//! fn token_uri(id: U256) -> String {
+//!     let svg = format!(
+//!         "<svg xmlns='http://www.w3.org/2000/svg'><text>{}</text></svg>",
+//!         lib_string::to_string(id),
+//!     );
+//!     let json = format!(
+//!         r#"{{"name":"Token #{}","image":"data:image/svg+xml;base64,{}"}}"#,
+//!         lib_string::to_string(id),
+//!         base64::encode(svg.as_bytes()),
+//!     );
+//!     format!("data:application/json;base64,{}", base64::encode(json.as_bytes()))
+//! }
+//! ```
+//!
+//! The helpers write bytes directly instead of going through `core::fmt`, which
+//! keeps them small once compiled to WASM.
+//!
+//! [`base64::encode`]: crate::utils::base64::encode
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use alloc::{
+    string::String,
+    vec::Vec,
+};
+use alloy_primitives::{
+    Address,
+    U256,
+};
+use stylus_sdk::crypto;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Returns the decimal representation of `value`.
+pub fn to_string(value: U256) -> String {
+    // 2^256 has 78 decimal digits.
+    let mut digits = [0u8; 78];
+    let mut start = digits.len();
+    let mut rest = value;
+    let ten = U256::from(10);
+
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (rest % ten).to::<u8>();
+        rest /= ten;
+
+        if rest == U256::ZERO {
+            break;
+        }
+    }
+
+    // Every byte written is an ASCII digit.
+    unsafe { String::from_utf8_unchecked(digits[start..].to_vec()) }
+}
+
+/// Returns the `0x`-prefixed hexadecimal representation of `value`, with as
+/// few bytes as possible but at least one.
+pub fn to_hex_string(value: U256) -> String {
+    let bytes = value.to_be_bytes::<32>();
+    let leading_zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+
+    hex_string(&bytes[leading_zeros.min(31)..])
+}
+
+/// Returns the 64 lowercase hexadecimal digits of `value`, without a prefix, as
+/// used in the `{id}` substitution of ERC-1155 URIs.
+pub fn to_hex_digits(value: U256) -> String {
+    let mut encoded = Vec::with_capacity(64);
+    push_hex(&mut encoded, &value.to_be_bytes::<32>());
+
+    // Every byte written is an ASCII hex digit.
+    unsafe { String::from_utf8_unchecked(encoded) }
+}
+
+/// Returns `address` as a `0x`-prefixed, EIP-55 checksummed string.
+pub fn to_checksum_hex_string(address: Address) -> String {
+    let mut encoded = hex_string(&address[..]).into_bytes();
+    let hash = crypto::keccak(&encoded[2..]);
+
+    // Uppercase each letter whose nibble in the hash of the lowercase hex is 8 or more.
+    for (index, character) in encoded[2..].iter_mut().enumerate() {
+        let nibble = (hash[index / 2] >> (4 * (1 - index % 2))) & 0x0f;
+        if nibble >= 8 {
+            character.make_ascii_uppercase();
+        }
+    }
+
+    // Every byte written is an ASCII hex digit.
+    unsafe { String::from_utf8_unchecked(encoded) }
+}
+
+/// Escapes `value` so that it can be embedded between double quotes in JSON.
+pub fn escape_json(value: &str) -> String {
+    let mut escaped = Vec::with_capacity(value.len());
+
+    for &byte in value.as_bytes() {
+        match byte {
+            b'"' => escaped.extend_from_slice(b"\\\""),
+            b'\\' => escaped.extend_from_slice(b"\\\\"),
+            b'\n' => escaped.extend_from_slice(b"\\n"),
+            b'\r' => escaped.extend_from_slice(b"\\r"),
+            b'\t' => escaped.extend_from_slice(b"\\t"),
+            0x08 => escaped.extend_from_slice(b"\\b"),
+            0x0c => escaped.extend_from_slice(b"\\f"),
+            0x00..=0x1f => {
+                escaped.extend_from_slice(b"\\u00");
+                escaped.push(HEX_DIGITS[(byte >> 4) as usize]);
+                escaped.push(HEX_DIGITS[(byte & 0x0f) as usize]);
+            }
+            _ => escaped.push(byte),
+        }
+    }
+
+    // Only ASCII bytes are added, and multi-byte characters are copied whole.
+    unsafe { String::from_utf8_unchecked(escaped) }
+}
+
+/// Returns `bytes` as a `0x`-prefixed lowercase hexadecimal string.
+fn hex_string(bytes: &[u8]) -> String {
+    let mut encoded = Vec::with_capacity(2 + bytes.len() * 2);
+    encoded.extend_from_slice(b"0x");
+    push_hex(&mut encoded, bytes);
+
+    // Every byte written is an ASCII hex digit.
+    unsafe { String::from_utf8_unchecked(encoded) }
+}
+
+fn push_hex(encoded: &mut Vec<u8>, bytes: &[u8]) {
+    for byte in bytes {
+        encoded.push(HEX_DIGITS[(byte >> 4) as usize]);
+        encoded.push(HEX_DIGITS[(byte & 0x0f) as usize]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Host;
+    use crate::utils::base64;
+    use alloy_primitives::address;
+
+    #[test]
+    fn decimal_string() {
+        assert_eq!(to_string(U256::ZERO), "0");
+        assert_eq!(to_string(U256::from(7)), "7");
+        assert_eq!(to_string(U256::from(1_234_567_890u64)), "1234567890");
+        assert_eq!(
+            to_string(U256::MAX),
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        );
+    }
+
+    #[test]
+    fn hex_string() {
+        assert_eq!(to_hex_string(U256::ZERO), "0x00");
+        assert_eq!(to_hex_string(U256::from(0xabc)), "0x0abc");
+        assert_eq!(to_hex_string(U256::from(0xdeadbeefu32)), "0xdeadbeef");
+        assert_eq!(to_hex_string(U256::MAX), ["0x", &"ff".repeat(32)].concat());
+        assert_eq!(to_hex_digits(U256::from(0x4cce0)), ["0".repeat(59), "4cce0".into()].concat());
+    }
+
+    // The test vectors from EIP-55.
+    #[test]
+    fn checksum_hex_string() {
+        let _host = Host::new();
+
+        for checksummed in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            let address: Address = checksummed.parse().unwrap();
+            assert_eq!(to_checksum_hex_string(address), checksummed);
+        }
+    }
+
+    #[test]
+    fn json_escaping() {
+        assert_eq!(escape_json("plain"), "plain");
+        assert_eq!(escape_json(r#"say "hi" \o/"#), r#"say \"hi\" \\o/"#);
+        assert_eq!(escape_json("a\nb\tc\u{1}"), "a\\nb\\tc\\u0001");
+        assert_eq!(escape_json("café ☕"), "café ☕");
+    }
+
+    #[test]
+    fn data_uri() {
+        let _host = Host::new();
+        let owner = address!("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        let json = [
+            r#"{"name":""#,
+            &escape_json("Token \"#1\""),
+            r#"","owner":""#,
+            &to_checksum_hex_string(owner),
+            r#""}"#,
+        ]
+        .concat();
+
+        assert_eq!(
+            json,
+            r##"{"name":"Token \"#1\"","owner":"0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"}"##
+        );
+        assert_eq!(
+            ["data:application/json;base64,", &base64::encode(json.as_bytes())].concat(),
+            concat!(
+                "data:application/json;base64,",
+                "eyJuYW1lIjoiVG9rZW4gXCIjMVwiIiwib3duZXIiOiIweDVhQWViNjA1M0YzRTk0QzliOUEwOWYz",
+                "MzY2OTQzNUU3RWYxQmVBZWQifQ==",
+            )
+        );
+    }
+}
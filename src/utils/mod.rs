@@ -1,2 +1,8 @@
+pub mod base64;
 pub mod bytes32address;
 pub mod create3;
+pub mod ecdsa;
+pub mod eip712;
+pub mod lib_string;
+pub mod namespace;
+pub mod signature_checker;
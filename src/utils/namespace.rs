@@ -0,0 +1,31 @@
+//! Provides access to ERC-7201 namespaced storage for the token extensions.
+//!
+//! Extensions driven by the transfer hooks of a token's params, such as
+//! [`ERC20Votes`](crate::tokens::erc20_votes::ERC20Votes) or
+//! [`ERC721Enumerable`](crate::tokens::erc721_enumerable::ERC721Enumerable),
+//! are only handed the storage of the token itself. They therefore keep their
+//! state at a fixed slot, derived from a `rustmate.<Name>` namespace as ERC-7201
+//! describes, and the extension field of the inheriting contract takes no
+//! storage. Namespaces start on a slot whose low byte is zero, so their layouts
+//! never overlap each other or the contract's own fields.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use alloy_primitives::{
+    B256,
+    U256,
+};
+use stylus_sdk::storage::StorageType;
+
+/// Returns a handle to the storage of type `S` at the namespace `slot`.
+///
+/// # Safety argument
+///
+/// A storage handle caches the values it reads, so two handles to the same
+/// slots alive at once could observe stale values after the other writes. Every
+/// caller in this crate loads at most one handle per method and drops it before
+/// the method returns, and never calls back into a hook or another contract
+/// while holding it, so no two handles to a namespace are ever alive together.
+pub(crate) fn load<S: StorageType>(slot: B256) -> S {
+    unsafe { S::new(U256::from_be_bytes(slot.0), 0) }
+}
@@ -0,0 +1,137 @@
+//! Provides signature verification for both externally owned accounts and
+//! smart contract wallets.
+//!
+//! [`is_valid_signature_now`] accepts ECDSA signatures from the signer's key,
+//! and otherwise asks the signer itself through ERC-1271's `isValidSignature`,
+//! so that accounts such as Safe wallets can sign too. As its name says, a
+//! contract's answer may change over time.
+//!
+//! Note that this code is unaudited and not fit for production use.
+
+use crate::utils::ecdsa;
+use alloc::vec::Vec;
+use alloy_primitives::{
+    Address,
+    B256,
+};
+use stylus_sdk::call::StaticCallContext;
+use stylus_sdk::prelude::*;
+
+/// `bytes4(keccak256("isValidSignature(bytes32,bytes)"))`, returned by ERC-1271
+/// wallets for the signatures they accept.
+const ERC1271_MAGIC_VALUE: u32 = 0x1626ba7e;
+
+sol_interface! {
+    interface IERC1271 {
+        function isValidSignature(bytes32 hash, bytes signature) external view returns (bytes4);
+    }
+}
+
+/// Whether `signature` over `hash` is valid for `signer`, either as an ECDSA
+/// signature by its key or by ERC-1271 if `signer` is a contract.
+pub fn is_valid_signature_now(
+    context: impl StaticCallContext,
+    signer: Address,
+    hash: B256,
+    signature: &[u8],
+) -> bool {
+    if let Ok(recovered) = ecdsa::recover_bytes(hash, signature) {
+        if recovered == signer {
+            return true;
+        }
+    }
+
+    is_valid_erc1271_signature_now(context, signer, hash, signature)
+}
+
+/// Whether the contract `signer` accepts `signature` over `hash` through
+/// ERC-1271. Accounts without code never do.
+pub fn is_valid_erc1271_signature_now(
+    context: impl StaticCallContext,
+    signer: Address,
+    hash: B256,
+    signature: &[u8],
+) -> bool {
+    let wallet = IERC1271::new(signer);
+
+    match wallet.is_valid_signature(context, hash, Vec::from(signature)) {
+        Ok(result) => u32::from_be_bytes(result.0) == ERC1271_MAGIC_VALUE,
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+    use alloy_primitives::address;
+
+    sol_storage! {
+        pub struct Verifier {}
+    }
+
+    unsafe impl TopLevelStorage for Verifier {}
+
+    const KEY: B256 = B256::repeat_byte(0x42);
+    const HASH: B256 = B256::repeat_byte(0xab);
+    const WALLET: Address = address!("00000000000000000000000000000000000a11e7");
+
+    fn signature(key: B256) -> Vec<u8> {
+        let (v, r, s) = sign(key, HASH);
+        [&r[..], &s[..], &[v]].concat()
+    }
+
+    fn magic_value(value: u32) -> Vec<u8> {
+        let mut word = [0u8; 32];
+        word[..4].copy_from_slice(&value.to_be_bytes());
+        word.to_vec()
+    }
+
+    #[test]
+    fn eoa_signature() {
+        let host = Host::new();
+        let verifier = host.contract::<Verifier>();
+
+        assert!(is_valid_signature_now(&verifier, signer(KEY), HASH, &signature(KEY)));
+        assert!(!is_valid_signature_now(
+            &verifier,
+            signer(KEY),
+            HASH,
+            &signature(B256::repeat_byte(0x43))
+        ));
+    }
+
+    #[test]
+    fn eoa_has_no_erc1271_signatures() {
+        let host = Host::new();
+        let verifier = host.contract::<Verifier>();
+
+        assert!(!is_valid_erc1271_signature_now(
+            &verifier,
+            signer(KEY),
+            HASH,
+            &signature(KEY)
+        ));
+    }
+
+    #[test]
+    fn wallet_accepts_signature() {
+        let host = Host::new();
+        let verifier = host.contract::<Verifier>();
+        host.mock_call(WALLET, Ok(magic_value(ERC1271_MAGIC_VALUE)));
+
+        assert!(is_valid_signature_now(&verifier, WALLET, HASH, &signature(KEY)));
+    }
+
+    #[test]
+    fn wallet_rejects_signature() {
+        let host = Host::new();
+        let verifier = host.contract::<Verifier>();
+
+        host.mock_call(WALLET, Ok(magic_value(0xffffffff)));
+        assert!(!is_valid_signature_now(&verifier, WALLET, HASH, &signature(KEY)));
+
+        host.mock_call(WALLET, Err(Vec::new()));
+        assert!(!is_valid_signature_now(&verifier, WALLET, HASH, &signature(KEY)));
+    }
+}